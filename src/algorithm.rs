@@ -0,0 +1,291 @@
+//! Storage-free core of the tree, compiles without `std` and `cosmwasm_std`.
+//! The hash of two nodes is supplied as a closure so the core does not depend on [Hasher](crate::Hasher).
+
+use alloc::{vec, vec::Vec};
+
+/// Check if the `position` is within the tree of `level`, the positions of a tree of level 64 span the whole `u64`.
+pub fn position_fits(position: u64, level: usize) -> bool {
+    u32::try_from(level).map_or(true, |level| {
+        position.checked_shr(level).unwrap_or_default() == 0
+    })
+}
+
+/// Check if `count` leaves fit in the tree of `level`.
+pub fn count_fits(count: u64, level: usize) -> bool {
+    count
+        .checked_sub(1)
+        .is_none_or(|position| position_fits(position, level))
+}
+
+/// Compute the hash of the empty subtree of each level, starting from the `default_leaf`.
+pub fn compute_zeros<L, E>(
+    level: u8,
+    default_leaf: L,
+    hash_two: impl Fn(&L, &L) -> Result<L, E>,
+) -> Result<Vec<L>, E> {
+    let mut zeros = vec![default_leaf];
+
+    for i in 1..level as usize {
+        let latest = &zeros[i - 1];
+        zeros.push(hash_two(latest, latest)?);
+    }
+
+    Ok(zeros)
+}
+
+/// Update the frontier `hashes` with the `leaf` at `position`, return the updated root.
+pub fn append<L, E>(
+    hashes: &mut [L],
+    zeros: &[L],
+    position: u64,
+    leaf: L,
+    hash_two: impl Fn(&L, &L) -> Result<L, E>,
+) -> Result<L, E> {
+    let mut cur_hash = leaf;
+    let mut cur_idx = position;
+
+    for i in 0..hashes.len() {
+        // Hash before moving the left node into the frontier to avoid cloning it
+        cur_hash = match cur_idx.is_multiple_of(2) {
+            true => {
+                let parent = hash_two(&cur_hash, &zeros[i])?;
+                hashes[i] = cur_hash;
+                parent
+            }
+            false => hash_two(&hashes[i], &cur_hash)?,
+        };
+        cur_idx /= 2;
+    }
+
+    Ok(cur_hash)
+}
+
+/// Compute the root of the tree holding `next_index` leaves from its `frontier`, like the root returned by [append].
+/// Return `None` if the frontier does not determine the root, i.e. the tree is full, the lengths do not match
+/// or the frontier has more than 64 levels.
+pub fn root_from_frontier<L: Clone, E>(
+    frontier: &[L],
+    zeros: &[L],
+    next_index: u64,
+    hash_two: impl Fn(&L, &L) -> Result<L, E>,
+) -> Result<Option<L>, E> {
+    if frontier.len() != zeros.len()
+        || frontier.len() > u64::BITS as usize
+        || !count_fits(next_index, frontier.len())
+    {
+        return Ok(None);
+    }
+
+    let Some(position) = next_index.checked_sub(1) else {
+        return Ok(zeros.last().cloned());
+    };
+
+    // The frontier holds the node of the last leaf at the lowest level where its path goes left,
+    // the right nodes below it are only hashed into that node.
+    let start = position.trailing_ones() as usize;
+    let Some(mut cur_hash) = frontier.get(start).cloned() else {
+        return Ok(None);
+    };
+
+    for i in start..frontier.len() {
+        cur_hash = match (position >> i) & 1 == 1 {
+            true => hash_two(&frontier[i], &cur_hash)?,
+            false => hash_two(&cur_hash, &zeros[i])?,
+        };
+    }
+
+    Ok(Some(cur_hash))
+}
+
+/// Check if appending the `leaves` to the tree holding `next_index` leaves with the `frontier` results in the `root`.
+/// The appended leaves must fit in the tree of at most 64 levels, the frontier is not checked against the root before the append.
+pub fn verify_append<L: Clone + PartialEq, E>(
+    frontier: &[L],
+    zeros: &[L],
+    next_index: u64,
+    leaves: &[L],
+    root: &L,
+    hash_two: impl Fn(&L, &L) -> Result<L, E>,
+) -> Result<bool, E> {
+    let fits = next_index
+        .checked_add(leaves.len() as u64)
+        .is_some_and(|count| count_fits(count, frontier.len()));
+    if frontier.len() != zeros.len() || frontier.len() > u64::BITS as usize || !fits {
+        return Ok(false);
+    }
+
+    let mut hashes = frontier.to_vec();
+    let mut appended_root = root_from_frontier(frontier, zeros, next_index, &hash_two)?;
+
+    for (position, leaf) in (next_index..).zip(leaves) {
+        appended_root = Some(append(
+            &mut hashes,
+            zeros,
+            position,
+            leaf.clone(),
+            &hash_two,
+        )?);
+    }
+
+    Ok(appended_root.as_ref() == Some(root))
+}
+
+/// Compute the root by folding the `leaf` with the `siblings` in the directions of the `index`.
+/// The nodes above level 64 are always the left child, the index has no bits for them.
+pub fn compute_root<L: Clone, E>(
+    leaf: &L,
+    index: u64,
+    siblings: &[L],
+    hash_two: impl Fn(&L, &L) -> Result<L, E>,
+) -> Result<L, E> {
+    siblings
+        .iter()
+        .enumerate()
+        .try_fold(leaf.clone(), |cur, (i, sibling)| {
+            match index.checked_shr(i as u32).unwrap_or_default() & 1 == 1 {
+                true => hash_two(sibling, &cur),
+                false => hash_two(&cur, sibling),
+            }
+        })
+}
+
+/// Check if the proof of `leaf` at `index` with the `siblings` is valid for the `root`.
+/// The proof is invalid if the index does not fit in the tree or it has more than 64 siblings.
+pub fn verify_proof<L: Clone + PartialEq, E>(
+    root: &L,
+    leaf: &L,
+    index: u64,
+    siblings: &[L],
+    hash_two: impl Fn(&L, &L) -> Result<L, E>,
+) -> Result<bool, E> {
+    if siblings.len() > u64::BITS as usize || index as u128 >= 1u128 << siblings.len() {
+        return Ok(false);
+    }
+
+    Ok(&compute_root(leaf, index, siblings, hash_two)? == root)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use super::{
+        append, compute_root, compute_zeros, count_fits, position_fits, root_from_frontier,
+        verify_append, verify_proof,
+    };
+
+    fn hash_two(left: &u64, right: &u64) -> Result<u64, Infallible> {
+        Ok(left.wrapping_mul(31).wrapping_add(*right).rotate_left(7))
+    }
+
+    #[test]
+    fn fits_without_overflow() {
+        assert!(position_fits(3, 2));
+        assert!(!position_fits(4, 2));
+        assert!(position_fits(u64::MAX, 64));
+        assert!(position_fits(u64::MAX, 200));
+        assert!(!position_fits(1, 0));
+
+        assert!(count_fits(4, 2));
+        assert!(!count_fits(5, 2));
+        assert!(count_fits(0, 0));
+        assert!(count_fits(1, 0));
+        assert!(count_fits(u64::MAX, 64));
+    }
+
+    #[test]
+    fn verify_proof_without_storage() {
+        let zeros = compute_zeros(3, 0, hash_two).unwrap();
+        let mut hashes = zeros.clone();
+        let mut root = 0;
+
+        for (position, leaf) in [1, 2, 3].into_iter().enumerate() {
+            root = append(&mut hashes, &zeros, position as u64, leaf, hash_two).unwrap();
+        }
+
+        let left = hash_two(&1, &2).unwrap();
+        let right = hash_two(&3, &zeros[0]).unwrap();
+
+        assert_eq!(
+            root,
+            hash_two(&hash_two(&left, &right).unwrap(), &zeros[2]).unwrap()
+        );
+        assert!(verify_proof(&root, &3, 2, &[zeros[0], left, zeros[2]], hash_two).unwrap());
+        assert!(!verify_proof(&root, &3, 3, &[zeros[0], left, zeros[2]], hash_two).unwrap());
+        assert!(!verify_proof(&root, &3, 10, &[zeros[0], left, zeros[2]], hash_two).unwrap());
+        assert!(!verify_proof(&root, &2, 2, &[zeros[0], left, zeros[2]], hash_two).unwrap());
+    }
+
+    #[test]
+    fn oversized_proof_without_storage() {
+        for depth in [65, 128, 200] {
+            let siblings = vec![0; depth];
+            let root = compute_root(&1, u64::MAX, &siblings, hash_two).unwrap();
+
+            assert!(!verify_proof(&root, &1, u64::MAX, &siblings, hash_two).unwrap());
+        }
+    }
+
+    #[test]
+    fn root_from_frontier_without_storage() {
+        let zeros = compute_zeros(3, 0, hash_two).unwrap();
+        let mut hashes = zeros.clone();
+
+        assert_eq!(
+            root_from_frontier(&hashes, &zeros, 0, hash_two).unwrap(),
+            Some(zeros[2])
+        );
+
+        for position in 0..8 {
+            let root = append(&mut hashes, &zeros, position, position + 1, hash_two).unwrap();
+            let expected = match position {
+                7 => None,
+                _ => Some(root),
+            };
+
+            assert_eq!(
+                root_from_frontier(&hashes, &zeros, position + 1, hash_two).unwrap(),
+                expected
+            );
+        }
+
+        assert_eq!(
+            root_from_frontier(&hashes[1..], &zeros, 1, hash_two).unwrap(),
+            None
+        );
+
+        for depth in [65, 128, 200] {
+            let frontier = vec![0; depth];
+
+            assert_eq!(
+                root_from_frontier(&frontier, &frontier, 1, hash_two).unwrap(),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn verify_append_without_storage() {
+        let zeros = compute_zeros(3, 0, hash_two).unwrap();
+        let mut hashes = zeros.clone();
+
+        append(&mut hashes, &zeros, 0, 1, hash_two).unwrap();
+
+        let frontier = hashes.clone();
+        let mut root = 0;
+        for (position, leaf) in [2, 3].into_iter().enumerate() {
+            root = append(&mut hashes, &zeros, position as u64 + 1, leaf, hash_two).unwrap();
+        }
+
+        assert!(verify_append(&frontier, &zeros, 1, &[2, 3], &root, hash_two).unwrap());
+        assert!(!verify_append(&frontier, &zeros, 1, &[2, 4], &root, hash_two).unwrap());
+        assert!(!verify_append(&frontier, &zeros, 1, &[2], &root, hash_two).unwrap());
+        assert!(!verify_append(&frontier, &zeros, 7, &[2, 3], &root, hash_two).unwrap());
+
+        for depth in [65, 128, 200] {
+            let frontier = vec![0; depth];
+
+            assert!(!verify_append(&frontier, &frontier, 1, &[2], &root, hash_two).unwrap());
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use cosmwasm_std::{Addr, Api, Binary, HexBinary};
+
+use crate::{Hasher, LeafBytes, MerkleTreeError};
+
+/// Encode the `leaf` as its canonical bytes, big-endian for [Uint256](cosmwasm_std::Uint256).
+pub fn leaf_to_bytes<L: LeafBytes>(leaf: &L) -> Vec<u8> {
+    leaf.leaf_bytes()
+}
+
+/// Decode the leaf from its canonical `bytes`.
+pub fn leaf_from_bytes<L: LeafBytes>(bytes: &[u8]) -> Result<L, MerkleTreeError> {
+    L::from_leaf_bytes(bytes)
+}
+
+/// Re-interpret the `leaf` as another leaf type sharing the same canonical bytes.
+/// E.g. convert a `Uint256` root into the `Vec<u8>` expected by an external proof system.
+pub fn convert_leaf<L: LeafBytes, T: LeafBytes>(leaf: &L) -> Result<T, MerkleTreeError> {
+    T::from_leaf_bytes(&leaf.leaf_bytes())
+}
+
+/// Hash the canonical bytes of the `addr` into a leaf, for allowlist trees over wallet addresses.
+/// The leaf is a plain hash, so it works with every tree including the ones keyed by root like
+/// [SparseMerkleTreeWithHistory](crate::tree::SparseMerkleTreeWithHistory) where [Addr] itself can not be used.
+/// Use `Vec<u8>` leaves unless every canonical address has the fixed length of `L`.
+pub fn addr_to_leaf<L: LeafBytes, H: Hasher<L>>(
+    api: &dyn Api,
+    addr: &Addr,
+    hasher: &H,
+) -> Result<L, MerkleTreeError> {
+    let canonical = api.addr_canonicalize(addr.as_str())?;
+
+    Ok(hasher.hash_leaf(&L::from_leaf_bytes(canonical.as_slice())?)?)
+}
+
+/// Encode the `leaf` as a lowercase hex string.
+pub fn leaf_to_hex<L: LeafBytes>(leaf: &L) -> String {
+    HexBinary::from(leaf.leaf_bytes()).to_hex()
+}
+
+/// Decode the leaf from the `hex` string.
+pub fn leaf_from_hex<L: LeafBytes>(hex: &str) -> Result<L, MerkleTreeError> {
+    L::from_leaf_bytes(HexBinary::from_hex(hex)?.as_slice())
+}
+
+/// Encode the `leaf` as a standard base64 string.
+pub fn leaf_to_base64<L: LeafBytes>(leaf: &L) -> String {
+    Binary::from(leaf.leaf_bytes()).to_base64()
+}
+
+/// Decode the leaf from the `base64` string.
+pub fn leaf_from_base64<L: LeafBytes>(base64: &str) -> Result<L, MerkleTreeError> {
+    L::from_leaf_bytes(Binary::from_base64(base64)?.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{
+        testing::{MockApi, MockStorage},
+        Addr, Uint256,
+    };
+
+    use crate::{
+        test_utils::Blake2,
+        tree::{SparseMerkleTree, SparseMerkleTreeWithHistory},
+        Hasher, MerkleTree, MerkleTreeError,
+    };
+
+    use super::{
+        addr_to_leaf, convert_leaf, leaf_from_base64, leaf_from_bytes, leaf_from_hex,
+        leaf_to_base64, leaf_to_bytes, leaf_to_hex,
+    };
+
+    const TREE: SparseMerkleTree<Uint256, Blake2> =
+        SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+    #[test]
+    fn latest_root_encoding() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let root = TREE.get_latest_root(&storage)?;
+        let hex = "1472fa12295e316bb84ee72fbbe17a0e4cb29ce279636730164f9dae5cf65c32";
+        let base64 = "FHL6EileMWu4Tucvu+F6DkyynOJ5Y2cwFk+drlz2XDI=";
+
+        assert_eq!(TREE.latest_root_hex(&storage)?, hex);
+        assert_eq!(TREE.latest_root_base64(&storage)?, base64);
+        assert_eq!(leaf_to_hex(&root), hex);
+        assert_eq!(leaf_to_base64(&root), base64);
+        assert_eq!(leaf_from_hex::<Uint256>(hex)?, root);
+        assert_eq!(leaf_from_base64::<Uint256>(base64)?, root);
+        assert_eq!(leaf_from_hex::<Vec<u8>>("0102")?, vec![1, 2]);
+        assert!(matches!(
+            leaf_from_hex::<[u8; 32]>("0102"),
+            Err(MerkleTreeError::InvalidLeaf(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn convert_leaf_round_trip() -> Result<(), Box<dyn Error>> {
+        let leaf = Uint256::from_u128(0x0102);
+        let mut bytes = vec![0; 30];
+        bytes.extend([1, 2]);
+
+        assert_eq!(leaf_to_bytes(&leaf), bytes);
+        assert_eq!(leaf_from_bytes::<Uint256>(&bytes)?, leaf);
+        assert_eq!(convert_leaf::<Uint256, Vec<u8>>(&leaf)?, bytes);
+        assert_eq!(convert_leaf::<Vec<u8>, Uint256>(&bytes)?, leaf);
+        assert_eq!(
+            convert_leaf::<Uint256, [u8; 32]>(&leaf)?,
+            <[u8; 32]>::try_from(bytes.as_slice())?
+        );
+        assert!(matches!(
+            convert_leaf::<Vec<u8>, Uint256>(&vec![1, 2]),
+            Err(MerkleTreeError::InvalidLeaf(_))
+        ));
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let root = TREE.latest_root_as::<Vec<u8>>(&storage)?;
+
+        assert_eq!(
+            leaf_from_bytes::<Uint256>(&root)?,
+            TREE.get_latest_root(&storage)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn addr_allowlist() -> Result<(), Box<dyn Error>> {
+        const ALLOWLIST: SparseMerkleTreeWithHistory<Vec<u8>, Blake2> =
+            SparseMerkleTreeWithHistory::new("hashes", "leafs", "level", "root", "root_history");
+
+        let api = MockApi::default();
+        let mut storage = MockStorage::new();
+
+        ALLOWLIST.init(&mut storage, 10, vec![0; 32], &Blake2)?;
+
+        let addrs = ["alice", "bob", "carol"].map(Addr::unchecked);
+        for addr in addrs.iter() {
+            ALLOWLIST.insert(&mut storage, addr_to_leaf(&api, addr, &Blake2)?, &Blake2)?;
+        }
+
+        let root = ALLOWLIST.get_latest_root(&storage)?;
+        let proof = ALLOWLIST.tree.gen_proof(&storage, 1, &Blake2)?;
+
+        assert!(proof.verify(&root, &addr_to_leaf(&api, &addrs[1], &Blake2)?, &Blake2)?);
+        assert!(!proof.verify(
+            &root,
+            &addr_to_leaf(&api, &Addr::unchecked("mallory"), &Blake2)?,
+            &Blake2
+        )?);
+        // Different casings of one address share the canonical bytes
+        assert_eq!(
+            addr_to_leaf::<Vec<u8>, _>(&api, &Addr::unchecked("BOB"), &Blake2)?,
+            addr_to_leaf::<Vec<u8>, _>(&api, &addrs[1], &Blake2)?
+        );
+
+        Ok(())
+    }
+}
@@ -14,10 +14,64 @@ pub enum MerkleTreeError {
 
     #[error("The tree is already initialized")]
     AlreadyInit,
+
+    #[error("The tree is already initialized with a different configuration")]
+    ConfigMismatch,
+
+    #[error("The tree is not initialized")]
+    NotInitialized,
+
+    #[error("The leaf is already inserted")]
+    DuplicateLeaf,
+
+    #[error("The leaf is the default leaf of an empty slot")]
+    DefaultLeaf,
+
+    #[error("Invalid leaf: {0}")]
+    InvalidLeaf(String),
+
+    #[error("Leaf of {size} bytes exceeds the maximum of {max} bytes")]
+    LeafTooLarge { size: u64, max: u64 },
+
+    #[error("Leaf at index {index} is not found")]
+    LeafNotFound { index: u64 },
+
+    #[error("The root is not found")]
+    RootNotFound,
+
+    #[error("Node at level {level} index {index} is out of range")]
+    NodeOutOfRange { level: u8, index: u64 },
+
+    #[error("Tree level must be at most 64")]
+    InvalidLevel,
+
+    #[error("History level must be greater than zero")]
+    InvalidHistoryLevel,
+
+    #[error("Every root in the root history is pinned")]
+    HistoryPinned,
+
+    #[error("Counter overflow")]
+    Overflow,
+
+    #[error("The tree is frozen")]
+    Frozen,
+
+    #[error("Invalid tree state: {0}")]
+    InvalidState(String),
+
+    #[error("Storage corruption: {0}")]
+    StorageCorruption(String),
 }
 
 #[derive(Debug, Error)]
 pub enum HasherError {
+    #[error("Invalid length: expected {expected} bytes, got {got} bytes")]
+    InvalidLength { expected: usize, got: usize },
+
+    #[error("Element is out of the field")]
+    OutOfField,
+
     #[error("{0}")]
     Custom(String),
 }
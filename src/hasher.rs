@@ -0,0 +1,149 @@
+use std::fmt::Debug;
+
+use crate::{Hasher, HasherError, MerkleTreeError};
+
+/// Hasher mixing the deployment-specific `salt` into every hash of the inner `hasher`.
+/// The salt is hashed with the left input first, so `hash_two(left, right)` is
+/// `hasher.hash_two(hasher.hash_two(salt, left), right)`, doubling the cost of each hash.
+///
+/// Trees with different salts never share a root or an internal node for the same leaves,
+/// a proof generated by one deployment can not be replayed against another.
+#[derive(Clone, Debug)]
+pub struct SaltedHasher<H, L> {
+    pub hasher: H,
+    pub salt: L,
+}
+
+impl<H, L> SaltedHasher<H, L> {
+    pub const fn new(hasher: H, salt: L) -> Self {
+        Self { hasher, salt }
+    }
+}
+
+impl<L: Clone + Debug, H: Hasher<L>> Hasher<L> for SaltedHasher<H, L> {
+    fn hash_two(&self, left: &L, right: &L) -> Result<L, HasherError> {
+        let salted = self.hasher.hash_two(&self.salt, left)?;
+
+        self.hasher.hash_two_owned(salted, right.clone())
+    }
+
+    fn hash_leaf(&self, preimage: &L) -> Result<L, HasherError> {
+        self.hasher.hash_leaf(preimage)
+    }
+
+    fn validate_leaf(&self, leaf: &L) -> Result<(), MerkleTreeError> {
+        self.hasher.validate_leaf(leaf)
+    }
+}
+
+/// Hasher prepending the length of each input as 8 big-endian bytes before hashing it with the inner `hasher`.
+/// `hash_two(left, right)` is `hasher.hash_two(len(left) || left, len(right) || right)`.
+///
+/// Hashers concatenating variable-length leaves like `Vec<u8>` map different splits of the same bytes to the same hash,
+/// e.g. `[1] || [2, 3]` and `[1, 2] || [3]`. With the length prefix the split is part of the hashed bytes,
+/// so two different pairs only collide if the inner hash function itself collides.
+#[derive(Clone, Debug)]
+pub struct LengthPrefixedHasher<H> {
+    pub hasher: H,
+}
+
+impl<H> LengthPrefixedHasher<H> {
+    pub const fn new(hasher: H) -> Self {
+        Self { hasher }
+    }
+
+    fn prefixed(input: &[u8]) -> Vec<u8> {
+        [&(input.len() as u64).to_be_bytes(), input].concat()
+    }
+}
+
+impl<H: Hasher<Vec<u8>>> Hasher<Vec<u8>> for LengthPrefixedHasher<H> {
+    fn hash_two(&self, left: &Vec<u8>, right: &Vec<u8>) -> Result<Vec<u8>, HasherError> {
+        self.hasher
+            .hash_two_owned(Self::prefixed(left), Self::prefixed(right))
+    }
+
+    fn hash_leaf(&self, preimage: &Vec<u8>) -> Result<Vec<u8>, HasherError> {
+        self.hasher.hash_leaf(preimage)
+    }
+
+    fn validate_leaf(&self, leaf: &Vec<u8>) -> Result<(), MerkleTreeError> {
+        self.hasher.validate_leaf(leaf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{test_utils::Blake2, tree::SparseMerkleTree, Hasher, MerkleTree};
+
+    use super::{LengthPrefixedHasher, SaltedHasher};
+
+    const TREE: SparseMerkleTree<Uint256, SaltedHasher<Blake2, Uint256>> =
+        SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+    #[test]
+    fn salted_roots() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut other_storage = MockStorage::new();
+        let hasher = SaltedHasher::new(Blake2, Uint256::from_u128(1));
+        let other_hasher = SaltedHasher::new(Blake2, Uint256::from_u128(2));
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &hasher)?;
+        TREE.init(&mut other_storage, 20, default_leaf, &other_hasher)?;
+
+        assert_ne!(
+            TREE.get_latest_root(&storage)?,
+            TREE.get_latest_root(&other_storage)?
+        );
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+
+            let (_, root) = TREE.insert(&mut storage, leaf, &hasher)?;
+            let (_, other_root) = TREE.insert(&mut other_storage, leaf, &other_hasher)?;
+
+            assert_ne!(root, other_root);
+
+            let proof = TREE.gen_proof(&storage, i as u64 - 1, &hasher)?;
+
+            assert!(proof.verify(&root, &leaf, &hasher)?);
+            assert!(!proof.verify(&root, &leaf, &other_hasher)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn length_prefixed() -> Result<(), Box<dyn Error>> {
+        let hasher = LengthPrefixedHasher::new(Blake2);
+
+        // Both splits concatenate to [1, 2, 3]
+        assert_eq!(
+            Blake2.hash_two(&vec![1], &vec![2, 3])?,
+            Blake2.hash_two(&vec![1, 2], &vec![3])?
+        );
+        assert_ne!(
+            hasher.hash_two(&vec![1], &vec![2, 3])?,
+            hasher.hash_two(&vec![1, 2], &vec![3])?
+        );
+        assert_eq!(
+            hasher.hash_two(&vec![1], &vec![2, 3])?,
+            Blake2.hash_two(
+                &vec![0, 0, 0, 0, 0, 0, 0, 1, 1],
+                &vec![0, 0, 0, 0, 0, 0, 0, 2, 2, 3]
+            )?
+        );
+        // The leaves are hashed by the inner hasher
+        assert_eq!(
+            hasher.hash_leaf(&vec![1, 2, 3])?,
+            Blake2.hash_leaf(&vec![1, 2, 3])?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use cosmwasm_std::{StdError, StdResult};
+use cw_storage_plus::{Key, KeyDeserialize, Prefixer, PrimaryKey};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{LeafBytes, MerkleTreeError};
+
+/// Fixed-size 32-byte leaf usable as a storage key, e.g. the root of [SparseMerkleTreeWithHistory](crate::tree::SparseMerkleTreeWithHistory).
+/// Unlike `Vec<u8>`, a leaf of any other length can not be constructed.
+#[derive(
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub struct Leaf32(pub [u8; 32]);
+
+impl From<[u8; 32]> for Leaf32 {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Leaf32> for [u8; 32] {
+    fn from(leaf: Leaf32) -> Self {
+        leaf.0
+    }
+}
+
+impl LeafBytes for Leaf32 {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn from_leaf_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        <[u8; 32]>::from_leaf_bytes(bytes).map(Self)
+    }
+}
+
+impl<'a> PrimaryKey<'a> for Leaf32 {
+    type Prefix = ();
+    type SubPrefix = ();
+    type Suffix = Self;
+    type SuperSuffix = Self;
+
+    fn key(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(&self.0)]
+    }
+}
+
+impl<'a> Prefixer<'a> for Leaf32 {
+    fn prefix(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(&self.0)]
+    }
+}
+
+impl KeyDeserialize for Leaf32 {
+    type Output = Self;
+
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        let len = value.len();
+
+        value
+            .try_into()
+            .map(Self)
+            .map_err(|_| StdError::invalid_data_size(32, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Order, StdResult};
+    use cw_storage_plus::{KeyDeserialize, Map};
+
+    use crate::{test_utils::Blake2, tree::SparseMerkleTreeWithHistory, MerkleTree};
+
+    use super::Leaf32;
+
+    const TREE: SparseMerkleTreeWithHistory<Leaf32, Blake2> =
+        SparseMerkleTreeWithHistory::new("hashes", "leafs", "level", "root", "root_history");
+
+    #[test]
+    fn history_tree() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 20, Leaf32([0; 32]), &Blake2)?;
+
+        let (_, first_root) = TREE.insert(&mut storage, Leaf32([1; 32]), &Blake2)?;
+        let (_, root) = TREE.insert(&mut storage, Leaf32([2; 32]), &Blake2)?;
+
+        assert!(TREE.is_valid_root(&storage, &first_root)?);
+        assert!(TREE.is_valid_root(&storage, &root)?);
+        assert!(!TREE.is_valid_root(&storage, &Leaf32([3; 32]))?);
+        assert!(TREE.prove_and_verify(&storage, 1, &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn key() -> Result<(), Box<dyn Error>> {
+        const MAP: Map<Leaf32, u64> = Map::new("map");
+
+        let mut storage = MockStorage::new();
+
+        MAP.save(&mut storage, Leaf32([2; 32]), &2)?;
+        MAP.save(&mut storage, Leaf32([1; 32]), &1)?;
+
+        assert_eq!(
+            MAP.range(&storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?,
+            vec![(Leaf32([1; 32]), 1), (Leaf32([2; 32]), 2)]
+        );
+        assert!(Leaf32::from_vec(vec![0; 31]).is_err());
+
+        Ok(())
+    }
+}
@@ -1,10 +1,44 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod algorithm;
+
+#[cfg(feature = "cosmwasm")]
+mod encoding;
+#[cfg(feature = "cosmwasm")]
 mod error;
+#[cfg(feature = "cosmwasm")]
+mod hasher;
+#[cfg(feature = "cosmwasm")]
+mod leaf;
+#[cfg(feature = "cosmwasm")]
+mod proof;
+#[cfg(feature = "cosmwasm")]
+mod state;
+#[cfg(feature = "cosmwasm")]
 mod r#trait;
 
+#[cfg(feature = "cosmwasm")]
 pub mod tree;
 
+#[cfg(feature = "query")]
+pub mod query;
+
+#[cfg(feature = "cosmwasm")]
+pub use encoding::*;
+#[cfg(feature = "cosmwasm")]
 pub use error::*;
+#[cfg(feature = "cosmwasm")]
+pub use hasher::*;
+#[cfg(feature = "cosmwasm")]
+pub use leaf::*;
+#[cfg(feature = "cosmwasm")]
+pub use proof::*;
+#[cfg(feature = "cosmwasm")]
 pub use r#trait::*;
+#[cfg(feature = "cosmwasm")]
+pub use state::*;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "cosmwasm"))]
 mod test_utils;
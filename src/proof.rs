@@ -0,0 +1,409 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{from_binary, to_binary, Binary, StdResult};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{algorithm, Hasher, HasherError};
+
+/// Merkle proof of a leaf, ordered from the leaf level up to the root.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof<L> {
+    /// Position of the proven leaf counted from the first leaf, its bits are the left/right directions.
+    /// The index already packs the directions of up to 64 levels, no separate compact form is needed.
+    pub index: u64,
+    /// Sibling hash at each level.
+    pub siblings: Vec<L>,
+}
+
+impl<L> MerkleProof<L> {
+    /// Serialize the proof into [Binary].
+    pub fn to_binary(&self) -> StdResult<Binary>
+    where
+        L: Serialize,
+    {
+        to_binary(self)
+    }
+
+    /// Deserialize the proof from [Binary].
+    pub fn from_binary(binary: &Binary) -> StdResult<Self>
+    where
+        L: DeserializeOwned,
+    {
+        from_binary(binary)
+    }
+
+    /// Get the direction of the proven node at each level, ordered from the leaf level up to the root like the siblings.
+    /// `true` if the node is the right child, i.e. bit `i` of the index is set and the node is hashed as `hash_two(siblings[i], node)`.
+    /// `false` if the node is the left child and hashed as `hash_two(node, siblings[i])`, the same convention as the insertion.
+    pub fn path_bits(&self) -> Vec<bool> {
+        (0..self.siblings.len() as u32)
+            .map(|i| self.index.checked_shr(i).unwrap_or_default() & 1 == 1)
+            .collect()
+    }
+
+    /// Compute the root by folding the `leaf` with the siblings in the directions of the index.
+    /// The nodes above level 64 are always the left child, like [MerkleProof::path_bits].
+    pub fn compute_root<H: Hasher<L>>(&self, leaf: &L, hasher: &H) -> Result<L, HasherError>
+    where
+        L: Clone,
+    {
+        algorithm::compute_root(leaf, self.index, &self.siblings, |left, right| {
+            hasher.hash_two(left, right)
+        })
+    }
+
+    /// Check if the proof of `leaf` is valid for the `root`.
+    /// The proof is invalid if the index does not fit in the tree or it has more than 64 siblings.
+    pub fn verify<H: Hasher<L>>(&self, root: &L, leaf: &L, hasher: &H) -> Result<bool, HasherError>
+    where
+        L: Clone + PartialEq,
+    {
+        algorithm::verify_proof(root, leaf, self.index, &self.siblings, |left, right| {
+            hasher.hash_two(left, right)
+        })
+    }
+
+    /// Check if the proof of `leaf` is valid for the `root` of a tree hashing the sorted pairs.
+    /// The index is ignored, each level hashes the smaller of the two nodes first.
+    pub fn verify_sorted<H: Hasher<L>>(
+        &self,
+        root: &L,
+        leaf: &L,
+        hasher: &H,
+    ) -> Result<bool, HasherError>
+    where
+        L: Clone + Ord,
+    {
+        let computed = self
+            .siblings
+            .iter()
+            .try_fold(leaf.clone(), |cur, sibling| match &cur <= sibling {
+                true => hasher.hash_two(&cur, sibling),
+                false => hasher.hash_two(sibling, &cur),
+            })?;
+
+        Ok(&computed == root)
+    }
+}
+
+/// Merkle proof of multiple leaves sharing the siblings, ordered from the leaf level up to the root.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct MultiProof<L> {
+    /// Sorted and deduplicated indices of the proven leaves.
+    pub indices: Vec<u64>,
+    /// Level of the tree.
+    pub level: u8,
+    /// Sibling hashes not derivable from the proven leaves, level by level in ascending index order.
+    pub siblings: Vec<L>,
+}
+
+impl<L> MultiProof<L> {
+    /// Check if the proof of `leaves` is valid for the `root`.
+    /// The `leaves` must be ordered like [MultiProof::indices].
+    pub fn verify<H: Hasher<L>>(
+        &self,
+        root: &L,
+        leaves: &[L],
+        hasher: &H,
+    ) -> Result<bool, HasherError>
+    where
+        L: Clone + PartialEq,
+    {
+        if leaves.len() != self.indices.len() || leaves.is_empty() {
+            return Ok(false);
+        }
+
+        let mut known = self
+            .indices
+            .iter()
+            .copied()
+            .zip(leaves.iter().cloned())
+            .collect::<BTreeMap<_, _>>();
+        let mut siblings = self.siblings.iter();
+
+        if known.len() != leaves.len() {
+            return Ok(false);
+        }
+
+        for _ in 0..self.level {
+            let mut next = BTreeMap::new();
+
+            for (idx, node) in known.iter() {
+                let parent = match idx % 2 == 0 {
+                    true => match known.get(&(idx + 1)).or_else(|| siblings.next()) {
+                        Some(right) => hasher.hash_two(node, right)?,
+                        None => return Ok(false),
+                    },
+                    false if known.contains_key(&(idx - 1)) => continue,
+                    false => match siblings.next() {
+                        Some(left) => hasher.hash_two(left, node)?,
+                        None => return Ok(false),
+                    },
+                };
+                next.insert(idx / 2, parent);
+            }
+
+            known = next;
+        }
+
+        Ok(siblings.next().is_none() && known.get(&0) == Some(root) && known.len() == 1)
+    }
+}
+
+/// Check if the `proof` of `leaf` is valid for the `root`, without any tree or storage.
+/// Meant for verifier-only contracts that are given the root, e.g. at instantiate.
+///
+/// ```
+/// use cw_merkle_tree::{verify_proof, Hasher, HasherError, MerkleProof};
+///
+/// #[derive(Clone, Debug)]
+/// struct Sum;
+///
+/// impl Hasher<u64> for Sum {
+///     fn hash_two(&self, left: &u64, right: &u64) -> Result<u64, HasherError> {
+///         Ok(left * 2 + right)
+///     }
+/// }
+///
+/// let proof = MerkleProof { index: 1, siblings: vec![3, 4] };
+///
+/// // Leaf 5 is the right child of 3, its parent is the left child of 4
+/// assert!(verify_proof(&Sum, &((3 * 2 + 5) * 2 + 4), &5, &proof)?);
+/// assert!(!verify_proof(&Sum, &0, &5, &proof)?);
+/// # Ok::<(), HasherError>(())
+/// ```
+pub fn verify_proof<L: Clone + PartialEq, H: Hasher<L>>(
+    hasher: &H,
+    root: &L,
+    leaf: &L,
+    proof: &MerkleProof<L>,
+) -> Result<bool, HasherError> {
+    proof.verify(root, leaf, hasher)
+}
+
+/// Check if the `proof` of the leaf hashed from the raw `preimage` with [Hasher::hash_leaf] is valid for the `root`.
+/// Meant for the leaves inserted with [MerkleTree::insert_raw](crate::MerkleTree::insert_raw), the caller only submits the preimage.
+pub fn verify_preimage_proof<L: Clone + PartialEq, H: Hasher<L>>(
+    hasher: &H,
+    root: &L,
+    preimage: &L,
+    proof: &MerkleProof<L>,
+) -> Result<bool, HasherError> {
+    proof.verify(root, &hasher.hash_leaf(preimage)?, hasher)
+}
+
+/// Check if the multi proof of `leaves` is valid for the `root`.
+pub fn verify_multi_proof<L: Clone + PartialEq, H: Hasher<L>>(
+    hasher: &H,
+    root: &L,
+    leaves: &[L],
+    proof: &MultiProof<L>,
+) -> Result<bool, HasherError> {
+    proof.verify(root, leaves, hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{
+        test_utils::{Blake2, NAMESPACES},
+        tree::SparseMerkleTree,
+        Hasher, MerkleTree,
+    };
+
+    use super::{verify_multi_proof, verify_preimage_proof, verify_proof, MerkleProof};
+
+    #[test]
+    fn binary_round_trip() -> Result<(), Box<dyn Error>> {
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+        let sibling = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let proof = MerkleProof {
+            index: 1,
+            siblings: vec![sibling, leaf],
+        };
+
+        let binary = proof.to_binary()?;
+
+        assert_eq!(MerkleProof::<Uint256>::from_binary(&binary)?, proof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_proof() -> Result<(), Box<dyn Error>> {
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+        let sibling = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        for depth in [65, 128, 200] {
+            let proof = MerkleProof {
+                index: u64::MAX,
+                siblings: vec![sibling; depth],
+            };
+            let root = proof.compute_root(&leaf, &Blake2)?;
+
+            assert_eq!(proof.path_bits().iter().filter(|bit| **bit).count(), 64);
+            assert!(!proof.verify(&root, &leaf, &Blake2)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn path_bits() -> Result<(), Box<dyn Error>> {
+        const TREE: SparseMerkleTree<Uint256, Blake2> =
+            SparseMerkleTree::from_namespaces(NAMESPACES);
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            3,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+        for i in 1..=6 {
+            TREE.insert(&mut storage, Uint256::from_u128(i), &Blake2)?;
+        }
+
+        for (index, bits) in [
+            (0, [false, false, false]),
+            (1, [true, false, false]),
+            (2, [false, true, false]),
+            (5, [true, false, true]),
+        ] {
+            assert_eq!(
+                TREE.gen_proof(&storage, index, &Blake2)?.path_bits(),
+                bits.to_vec()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_proof_from_tree() -> Result<(), Box<dyn Error>> {
+        const TREE: SparseMerkleTree<Uint256, Blake2> =
+            SparseMerkleTree::from_namespaces(NAMESPACES);
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        // Only the root, the leaf and the proof reach the verifier
+        let root = TREE.get_latest_root(&storage)?;
+        let proof = TREE.gen_proof(&storage, 3, &Blake2)?;
+        let multi_proof = TREE.gen_multi_proof(&storage, &[1, 3], &Blake2)?;
+
+        assert!(verify_proof(&Blake2, &root, &leafs[3], &proof)?);
+        assert!(!verify_proof(&Blake2, &root, &leafs[2], &proof)?);
+        assert!(verify_multi_proof(
+            &Blake2,
+            &root,
+            &[leafs[1], leafs[3]],
+            &multi_proof
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_preimage_proof_from_tree() -> Result<(), Box<dyn Error>> {
+        const TREE: SparseMerkleTree<Uint256, Blake2> =
+            SparseMerkleTree::from_namespaces(NAMESPACES);
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=5 {
+            TREE.insert_raw(&mut storage, Uint256::from_u128(i), &Blake2)?;
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+        let proof = TREE.gen_proof(&storage, 2, &Blake2)?;
+        let preimage = Uint256::from_u128(3);
+
+        assert_eq!(
+            verify_preimage_proof(&Blake2, &root, &preimage, &proof)?,
+            verify_proof(&Blake2, &root, &Blake2.hash_leaf(&preimage)?, &proof)?
+        );
+        assert!(verify_preimage_proof(&Blake2, &root, &preimage, &proof)?);
+        assert!(!verify_preimage_proof(
+            &Blake2,
+            &root,
+            &Uint256::from_u128(4),
+            &proof
+        )?);
+        // The stored leaf is already hashed, it is not the preimage
+        assert!(!verify_preimage_proof(
+            &Blake2,
+            &root,
+            &TREE.get_leaf(&storage, 2)?.unwrap(),
+            &proof
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_flipped_directions() -> Result<(), Box<dyn Error>> {
+        const TREE: SparseMerkleTree<Uint256, Blake2> =
+            SparseMerkleTree::from_namespaces(NAMESPACES);
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+
+        for (index, leaf) in leafs.iter().enumerate() {
+            let mut proof = TREE.gen_proof(&storage, index as u64, &Blake2)?;
+
+            assert!(proof.verify(&root, leaf, &Blake2)?);
+
+            proof.index ^= 1;
+            assert!(!proof.verify(&root, leaf, &Blake2)?);
+
+            // A direction bit above the depth does not fit in the tree
+            proof.index ^= 1 << 20;
+            assert!(!proof.verify(&root, leaf, &Blake2)?);
+        }
+
+        Ok(())
+    }
+}
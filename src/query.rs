@@ -0,0 +1,143 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::{to_binary, Binary, Storage};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{Hasher, MerkleTree, MerkleTreeError};
+
+/// Common queries of the tree, can be embedded into the contract's query message.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MerkleQueryMsg<L> {
+    /// Return [LatestRootResponse].
+    LatestRoot {},
+    /// Return [IsValidRootResponse].
+    IsValidRoot { root: L },
+    /// Return [LeafResponse].
+    Leaf { index: u64 },
+    /// Return [LeafCountResponse].
+    LeafCount {},
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct LatestRootResponse<L> {
+    pub root: L,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct IsValidRootResponse {
+    pub is_valid: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct LeafResponse<L> {
+    pub leaf: Option<L>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct LeafCountResponse {
+    pub count: u64,
+}
+
+/// Handle the `msg` against the `tree` and return the serialized response.
+pub fn handle_query<
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+    H: Hasher<L>,
+    T: MerkleTree<L, H>,
+>(
+    tree: &T,
+    storage: &dyn Storage,
+    msg: MerkleQueryMsg<L>,
+) -> Result<Binary, MerkleTreeError> {
+    let response = match msg {
+        MerkleQueryMsg::LatestRoot {} => to_binary(&LatestRootResponse {
+            root: tree.get_latest_root(storage)?,
+        }),
+        MerkleQueryMsg::IsValidRoot { root } => to_binary(&IsValidRootResponse {
+            is_valid: tree.is_valid_root(storage, &root)?,
+        }),
+        MerkleQueryMsg::Leaf { index } => to_binary(&LeafResponse {
+            leaf: tree.get_leaf(storage, index)?,
+        }),
+        MerkleQueryMsg::LeafCount {} => to_binary(&LeafCountResponse {
+            count: tree.leaf_count(storage)?,
+        }),
+    }?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{from_binary, testing::MockStorage, Uint256};
+
+    use crate::{test_utils::Blake2, tree::SparseMerkleTreeWithHistory, Hasher, MerkleTree};
+
+    use super::{
+        handle_query, IsValidRootResponse, LatestRootResponse, LeafCountResponse, LeafResponse,
+        MerkleQueryMsg,
+    };
+
+    const TREE: SparseMerkleTreeWithHistory<Vec<u8>, Blake2> =
+        SparseMerkleTreeWithHistory::new("hashes", "leafs", "level", "root", "root_history");
+
+    #[test]
+    fn handle_query_variants() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = Uint256::zero().to_be_bytes().to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+        let (_, root) = TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+
+        let latest_root: LatestRootResponse<Vec<u8>> = from_binary(&handle_query(
+            &TREE,
+            &storage,
+            MerkleQueryMsg::LatestRoot {},
+        )?)?;
+        let is_valid_root: IsValidRootResponse = from_binary(&handle_query(
+            &TREE,
+            &storage,
+            MerkleQueryMsg::IsValidRoot { root: root.clone() },
+        )?)?;
+        let is_invalid_root: IsValidRootResponse = from_binary(&handle_query(
+            &TREE,
+            &storage,
+            MerkleQueryMsg::IsValidRoot { root: leaf.clone() },
+        )?)?;
+        let inserted_leaf: LeafResponse<Vec<u8>> = from_binary(&handle_query(
+            &TREE,
+            &storage,
+            MerkleQueryMsg::Leaf { index: 0 },
+        )?)?;
+        let empty_leaf: LeafResponse<Vec<u8>> = from_binary(&handle_query(
+            &TREE,
+            &storage,
+            MerkleQueryMsg::Leaf { index: 1 },
+        )?)?;
+        let leaf_count: LeafCountResponse = from_binary(&handle_query(
+            &TREE,
+            &storage,
+            MerkleQueryMsg::LeafCount {},
+        )?)?;
+
+        assert_eq!(latest_root.root, root);
+        assert!(is_valid_root.is_valid);
+        assert!(!is_invalid_root.is_valid);
+        assert_eq!(inserted_leaf.leaf, Some(leaf));
+        assert_eq!(empty_leaf.leaf, None);
+        assert_eq!(leaf_count.count, 1);
+
+        Ok(())
+    }
+}
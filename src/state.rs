@@ -0,0 +1,96 @@
+use std::fmt::Debug;
+
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    algorithm,
+    tree::{Padding, SparseMerkleTree},
+    Hasher, MerkleTreeError,
+};
+
+/// Full stored state of a tree, used for exporting and importing the tree between storages.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct TreeState<L> {
+    pub level: u8,
+    pub frontier: Vec<L>,
+    pub zeros: Vec<L>,
+    #[serde(default)]
+    pub start_index: u64,
+    #[serde(default)]
+    pub max_leaf_bytes: Option<u64>,
+    #[serde(default)]
+    pub padding: Padding,
+    /// Set if the tree is frozen by [SparseMerkleTree::freeze].
+    #[serde(default)]
+    pub frozen: bool,
+    pub root: Option<L>,
+    pub leafs: Vec<(u64, L)>,
+}
+
+/// Configuration of a tree written once on init, see [SparseMerkleTree::config].
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct TreeConfig<L> {
+    pub level: u8,
+    pub default_leaf: L,
+    /// Number of children of each node, always 2 for the binary trees.
+    pub arity: u8,
+    /// [Hasher::id] of the hasher the tree is initialized with, `None` if the hasher has no id.
+    #[serde(default)]
+    pub hasher: Option<String>,
+}
+
+/// Storage usage of a tree, see [SparseMerkleTree::storage_stats].
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Number of stored leaves.
+    pub leaves: u64,
+    /// Number of retained history roots.
+    pub history_roots: u64,
+    /// Maximum number of retained history roots, `None` if unbounded.
+    pub max_history_roots: Option<u64>,
+    /// Estimated size of the stored values in bytes, storage keys are not counted.
+    pub estimated_bytes: u64,
+}
+
+/// Usage counters of a tree, see [InstrumentedTree::metrics](crate::tree::InstrumentedTree::metrics).
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct TreeMetrics {
+    /// Number of inserted leaves.
+    pub inserts: u64,
+    /// Number of counted root checks.
+    pub root_checks: u64,
+}
+
+/// Frontier of a tree, enough to append leaves and compute the root without the inserted leaves.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct Frontier<L> {
+    pub frontier: Vec<L>,
+    pub zeros: Vec<L>,
+    /// Position of the next leaf, counted from the first leaf of the tree.
+    pub next_index: u64,
+}
+
+impl<L: Serialize + DeserializeOwned + Clone + Debug + PartialEq> Frontier<L> {
+    /// Append the `leaf` at `next_index` like [MerkleTree::insert](crate::MerkleTree::insert), return the updated root.
+    /// Return [MerkleTreeError::InvalidLevel] if the frontier has more than 64 levels.
+    pub fn append<H: Hasher<L>>(&mut self, leaf: L, hasher: &H) -> Result<L, MerkleTreeError> {
+        (self.frontier.len() <= u64::BITS as usize)
+            .then_some(())
+            .ok_or(MerkleTreeError::InvalidLevel)?;
+        algorithm::position_fits(self.next_index, self.frontier.len())
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+
+        let root = SparseMerkleTree::<L, H>::append(
+            &mut self.frontier,
+            &self.zeros,
+            self.next_index,
+            leaf,
+            hasher,
+        )?;
+        self.next_index += 1;
+
+        Ok(root)
+    }
+}
@@ -1,13 +1,23 @@
-use std::{array::TryFromSliceError, error::Error, str::FromStr};
+use std::{error::Error, str::FromStr};
 
 use blake2::{Blake2b512, Digest};
 use cosmwasm_std::Uint256;
 
-use crate::{Hasher, HasherError};
+use crate::{tree::TreeNamespaces, Hasher, HasherError, Leaf32, ZeroLeaf};
+
+/// Namespaces of the tree shared by the tests.
+pub const NAMESPACES: TreeNamespaces = TreeNamespaces::new("hashes", "leafs", "level", "root");
 
 #[derive(Clone, Copy, Debug)]
 pub struct Blake2;
 
+fn digest_bytes(bytes: &[u8]) -> Result<[u8; 32], HasherError> {
+    bytes.try_into().map_err(|_| HasherError::InvalidLength {
+        expected: 32,
+        got: bytes.len(),
+    })
+}
+
 impl Hasher<Vec<u8>> for Blake2 {
     fn hash_two(&self, left: &Vec<u8>, right: &Vec<u8>) -> Result<Vec<u8>, HasherError> {
         let mut hasher = Blake2b512::new();
@@ -17,16 +27,39 @@ impl Hasher<Vec<u8>> for Blake2 {
     }
 }
 
+impl Hasher<[u8; 32]> for Blake2 {
+    fn hash_two(&self, left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32], HasherError> {
+        let mut hasher = Blake2b512::new();
+        hasher.update(left);
+        hasher.update(right);
+        digest_bytes(&hasher.finalize()[0..32])
+    }
+}
+
+impl Hasher<Leaf32> for Blake2 {
+    fn hash_two(&self, left: &Leaf32, right: &Leaf32) -> Result<Leaf32, HasherError> {
+        self.hash_two(&left.0, &right.0).map(Leaf32)
+    }
+}
+
 impl Hasher<Uint256> for Blake2 {
+    fn id(&self) -> Option<String> {
+        Some("blake2".to_string())
+    }
+
     fn hash_two(&self, left: &Uint256, right: &Uint256) -> Result<Uint256, HasherError> {
         let mut hasher = Blake2b512::new();
         hasher.update(left.to_be_bytes());
         hasher.update(right.to_be_bytes());
-        Ok(Uint256::from_be_bytes(
-            hasher.finalize()[0..32]
-                .try_into()
-                .map_err(|e: TryFromSliceError| HasherError::Custom(e.to_string()))?,
-        ))
+        Ok(Uint256::from_be_bytes(digest_bytes(
+            &hasher.finalize()[0..32],
+        )?))
+    }
+}
+
+impl ZeroLeaf<Uint256> for Blake2 {
+    fn zero_leaf(&self) -> Result<Uint256, HasherError> {
+        self.hash_two(&Uint256::zero(), &Uint256::zero())
     }
 }
 
@@ -43,3 +76,31 @@ fn hash() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn hash_two_owned() -> Result<(), Box<dyn Error>> {
+    let left = Uint256::from_u128(1);
+    let right = Uint256::from_u128(2);
+
+    assert_eq!(
+        Blake2.hash_two_owned(left, right)?,
+        Blake2.hash_two(&left, &right)?
+    );
+    assert_eq!(
+        Blake2.hash_two_owned(left.to_be_bytes().to_vec(), right.to_be_bytes().to_vec())?,
+        Blake2.hash_two(&left.to_be_bytes().to_vec(), &right.to_be_bytes().to_vec())?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn digest_invalid_length() {
+    assert!(matches!(
+        digest_bytes(&[0; 64]),
+        Err(HasherError::InvalidLength {
+            expected: 32,
+            got: 64
+        })
+    ));
+}
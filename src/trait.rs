@@ -1,17 +1,136 @@
 use std::fmt::Debug;
 
-use cosmwasm_std::Storage;
-use serde::{de::DeserializeOwned, Serialize};
+use cosmwasm_std::{attr, Attribute, Storage, Uint256};
+use cw_storage_plus::{KeyDeserialize, PrimaryKey};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::{HasherError, MerkleTreeError};
+use crate::{
+    algorithm, convert_leaf, leaf_to_base64, leaf_to_hex, HasherError, MerkleProof, MerkleTreeError,
+};
 
 pub trait Hasher<T>: Clone + Debug {
     /// Hash two elements together.
     fn hash_two(&self, left: &T, right: &T) -> Result<T, HasherError>;
+
+    /// Hash two owned elements together, used when the elements are no longer needed.
+    /// Override it to reuse the elements' allocation instead of cloning them.
+    fn hash_two_owned(&self, left: T, right: T) -> Result<T, HasherError> {
+        self.hash_two(&left, &right)
+    }
+
+    /// Hash the raw `preimage` into the leaf of the tree.
+    /// The preimage is hashed with itself by default.
+    fn hash_leaf(&self, preimage: &T) -> Result<T, HasherError> {
+        self.hash_two(preimage, preimage)
+    }
+
+    /// Identifier of the hasher saved in the [TreeConfig](crate::TreeConfig) on init, `None` by default.
+    /// Override it with a fixed name to reject the trees initialized with another hasher.
+    fn id(&self) -> Option<String> {
+        None
+    }
+
+    /// Validate the `leaf` before it is inserted into the tree.
+    /// Every leaf is valid by default, override it to reject malformed leaves.
+    fn validate_leaf(&self, _leaf: &T) -> Result<(), MerkleTreeError> {
+        Ok(())
+    }
+}
+
+/// Hasher with a canonical default leaf, used by [MerkleTree::init_default].
+/// Implement it to fix the default leaf of every deployment using the hasher.
+pub trait ZeroLeaf<T>: Hasher<T> {
+    /// Get the default leaf filling the empty slots of the tree.
+    fn zero_leaf(&self) -> Result<T, HasherError>;
+}
+
+/// Leaf type with a canonical byte representation.
+pub trait LeafBytes: Sized {
+    /// Get the bytes representation of the leaf.
+    fn leaf_bytes(&self) -> Vec<u8>;
+
+    /// Get the leaf from its bytes representation.
+    fn from_leaf_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError>;
+}
+
+impl LeafBytes for Vec<u8> {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_leaf_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl LeafBytes for [u8; 32] {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn from_leaf_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        bytes.try_into().map_err(|_| {
+            MerkleTreeError::InvalidLeaf(format!("expected 32 bytes, got {}", bytes.len()))
+        })
+    }
+}
+
+impl LeafBytes for Uint256 {
+    fn leaf_bytes(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+
+    fn from_leaf_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        <[u8; 32]>::from_leaf_bytes(bytes).map(Uint256::from_be_bytes)
+    }
+}
+
+/// Integer type used as the storage key of the leaves, the indices are still exposed as `u64`.
+/// Only the types no wider than `u64` are keys, so every key converts back into its index without truncation.
+/// The indices beyond the key type are rejected with [MerkleTreeError::ExceedMaxLeaf] on insert.
+pub trait LeafIndex:
+    for<'k> PrimaryKey<'k> + KeyDeserialize<Output = Self> + Copy + Debug + PartialEq + 'static
+{
+    /// Convert the `index` into the key, return `None` if it does not fit.
+    fn from_index(index: u64) -> Option<Self>;
+
+    /// Convert the key back into the index.
+    fn into_index(self) -> u64;
+}
+
+macro_rules! impl_leaf_index {
+    ($($t:ty),*) => {
+        $(
+            impl LeafIndex for $t {
+                fn from_index(index: u64) -> Option<Self> {
+                    index.try_into().ok()
+                }
+
+                fn into_index(self) -> u64 {
+                    self.into()
+                }
+            }
+        )*
+    };
+}
+
+impl_leaf_index!(u8, u16, u32, u64);
+
+/// Result of [MerkleTree::insert_checked].
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct InsertResult<L> {
+    /// Index of the inserted leaf.
+    pub index: u64,
+    /// Updated root.
+    pub root: L,
+    /// Whether the inserted leaf filled the last slot of the tree.
+    pub is_full: bool,
 }
 
 pub trait MerkleTree<L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>> {
     /// Initize the tree.
+    /// Return [MerkleTreeError::InvalidLevel] if the `level` is above 64, the leaf positions are `u64`.
     fn init(
         &self,
         storage: &mut dyn Storage,
@@ -20,10 +139,54 @@ pub trait MerkleTree<L: Serialize + DeserializeOwned + Clone + Debug + PartialEq
         hasher: &H,
     ) -> Result<(), MerkleTreeError>;
 
+    /// Initialize the tree like [MerkleTree::init] with the default leaf derived from `seed`.
+    /// The default leaf is `hasher.hash_leaf(seed)`.
+    fn init_with_seed(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        seed: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        let default_leaf = hasher.hash_leaf(&seed)?;
+
+        self.init(storage, level, default_leaf, hasher)
+    }
+
+    /// Initialize the tree like [MerkleTree::init] with the default leaf of the hasher, see [ZeroLeaf].
+    fn init_default(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError>
+    where
+        H: ZeroLeaf<L>,
+    {
+        self.init(storage, level, hasher.zero_leaf()?, hasher)
+    }
+
     /// Check if the `root` is valid for the tree.
     fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError>;
 
+    /// Count the distinct roots currently valid for [MerkleTree::is_valid_root].
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError>;
+
+    /// Check if each of the `roots` is valid for the tree like [MerkleTree::is_valid_root].
+    /// Return the validity of each root in the same order.
+    fn validate_roots(
+        &self,
+        storage: &dyn Storage,
+        roots: &[L],
+    ) -> Result<Vec<bool>, MerkleTreeError> {
+        roots
+            .iter()
+            .map(|root| self.is_valid_root(storage, root))
+            .collect()
+    }
+
     /// Insert the `leaf` into the next index slot of the tree.
+    /// The `leaf` is inserted as is, it should already be the final leaf hash.
     /// Return the leaf's index and the updated root.
     fn insert(
         &self,
@@ -32,6 +195,227 @@ pub trait MerkleTree<L: Serialize + DeserializeOwned + Clone + Debug + PartialEq
         hasher: &H,
     ) -> Result<(u64, L), MerkleTreeError>;
 
+    /// Insert the `leaf` like [MerkleTree::insert], also return the proof of the leaf against the updated root.
+    /// The proof is built from the path hashed by the insertion instead of replaying the inserted leaves.
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError>;
+
+    /// Hash the raw `preimage` with [Hasher::hash_leaf] and insert it like [MerkleTree::insert].
+    /// Only the hashed leaf is stored, the preimage never reaches the storage and can be kept off-chain.
+    /// [MerkleTree::get_leaf] and the proofs then operate on the hashed leaf, not on the preimage.
+    fn insert_raw(
+        &self,
+        storage: &mut dyn Storage,
+        preimage: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        let leaf = hasher.hash_leaf(&preimage)?;
+
+        self.insert(storage, leaf, hasher)
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert].
+    /// Return [MerkleTreeError::DefaultLeaf] if the `leaf` is the default leaf, which is indistinguishable from an empty slot.
+    fn insert_nonzero(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        (leaf != self.default_leaf(storage)?)
+            .then_some(())
+            .ok_or(MerkleTreeError::DefaultLeaf)?;
+
+        self.insert(storage, leaf, hasher)
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert].
+    /// Return `None` instead of [MerkleTreeError::ExceedMaxLeaf] if the tree is already full.
+    fn try_insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<Option<(u64, L)>, MerkleTreeError> {
+        match self.insert(storage, leaf, hasher) {
+            Ok(inserted) => Ok(Some(inserted)),
+            Err(MerkleTreeError::ExceedMaxLeaf) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert].
+    /// Also return whether the tree is full after the insertion.
+    fn insert_checked(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<InsertResult<L>, MerkleTreeError> {
+        let (index, root) = self.insert(storage, leaf, hasher)?;
+
+        Ok(InsertResult {
+            index,
+            root,
+            is_full: self.is_full(storage)?,
+        })
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert].
+    /// Also return the attributes of the leaf's index and the hex-encoded updated root.
+    fn insert_with_event(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, Vec<Attribute>), MerkleTreeError>
+    where
+        L: LeafBytes,
+    {
+        let (index, root) = self.insert(storage, leaf, hasher)?;
+        let attributes = vec![
+            attr("leaf_index", index.to_string()),
+            attr("root", leaf_to_hex(&root)),
+        ];
+
+        Ok((index, root, attributes))
+    }
+
+    /// Insert every leaf of `leaves` in order like [MerkleTree::insert].
+    /// Return the leaves' indices and the final root, the latest root if `leaves` is empty.
+    /// Nothing is inserted and [MerkleTreeError::ExceedMaxLeaf] is returned if the leaves do not all fit.
+    fn insert_from_iter<I: IntoIterator<Item = L>>(
+        &self,
+        storage: &mut dyn Storage,
+        leaves: I,
+        hasher: &H,
+    ) -> Result<(Vec<u64>, L), MerkleTreeError> {
+        let leaves = leaves.into_iter().collect::<Vec<_>>();
+        let level = self.level(storage)? as usize;
+
+        self.leaf_count(storage)?
+            .checked_add(leaves.len() as u64)
+            .is_some_and(|count| algorithm::count_fits(count, level))
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+
+        let mut indices = Vec::with_capacity(leaves.len());
+        let mut root = None;
+
+        for leaf in leaves {
+            let (index, updated_root) = self.insert(storage, leaf, hasher)?;
+            indices.push(index);
+            root = Some(updated_root);
+        }
+
+        let root = match root {
+            Some(root) => root,
+            None => self.get_latest_root(storage)?,
+        };
+
+        Ok((indices, root))
+    }
+
     /// Get the latest root of the tree.
     fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError>;
+
+    /// Get the latest root of the tree as a hex string.
+    fn latest_root_hex(&self, storage: &dyn Storage) -> Result<String, MerkleTreeError>
+    where
+        L: LeafBytes,
+    {
+        Ok(leaf_to_hex(&self.get_latest_root(storage)?))
+    }
+
+    /// Get the latest root of the tree re-interpreted as another leaf type like [convert_leaf].
+    fn latest_root_as<T: LeafBytes>(&self, storage: &dyn Storage) -> Result<T, MerkleTreeError>
+    where
+        L: LeafBytes,
+    {
+        convert_leaf(&self.get_latest_root(storage)?)
+    }
+
+    /// Get the latest root of the tree as a base64 string.
+    fn latest_root_base64(&self, storage: &dyn Storage) -> Result<String, MerkleTreeError>
+    where
+        L: LeafBytes,
+    {
+        Ok(leaf_to_base64(&self.get_latest_root(storage)?))
+    }
+
+    /// Get the leaf at `index`, return `None` if the slot is not inserted yet.
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError>;
+
+    /// Get the leaf at each of the `indices` like [MerkleTree::get_leaf], in the same order.
+    fn get_leaves(
+        &self,
+        storage: &dyn Storage,
+        indices: &[u64],
+    ) -> Result<Vec<Option<L>>, MerkleTreeError> {
+        indices
+            .iter()
+            .map(|index| self.get_leaf(storage, *index))
+            .collect()
+    }
+
+    /// Get the level of the tree, return [MerkleTreeError::NotInitialized] if the tree is not initialized.
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError>;
+
+    /// Get the default leaf the tree is initialized with, return [MerkleTreeError::NotInitialized] if the tree is not initialized.
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError>;
+
+    /// Get the hash of the empty subtree of each level as stored on init, ordered from the leaf level.
+    /// External verifiers can use them to pad a frontier without recomputing them.
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError>;
+
+    /// Get the number of inserted leaves.
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError>;
+
+    /// Check if all leaf slots of the tree are inserted.
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError>;
+
+    /// Get the number of leaves that can still be inserted before the tree is full.
+    /// Saturate at [u64::MAX] if the capacity of the tree does not fit.
+    fn remaining_capacity(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        let capacity = 1u128
+            .checked_shl(self.level(storage)? as u32)
+            .unwrap_or(u128::MAX);
+        let remaining = capacity.saturating_sub(self.leaf_count(storage)? as u128);
+
+        Ok(remaining.min(u64::MAX as u128) as u64)
+    }
+
+    /// Generate the proof of the leaf at `index` and verify it against the latest root.
+    /// Return [MerkleTreeError::LeafNotFound] if the leaf at `index` is not inserted.
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError>;
+
+    /// Check if the `proof` of `leaf` leads to a root valid for [MerkleTree::is_valid_root].
+    /// The proof must be as deep as the tree, the root it leads to is not required to be the latest.
+    fn check_inclusion(
+        &self,
+        storage: &dyn Storage,
+        leaf: &L,
+        proof: &MerkleProof<L>,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        let level = self.level(storage)? as usize;
+
+        if proof.siblings.len() != level || !algorithm::position_fits(proof.index, level) {
+            return Ok(false);
+        }
+
+        self.is_valid_root(storage, &proof.compute_root(leaf, hasher)?)
+    }
+
+    /// Remove all stored states of the tree, the tree can be initialized again afterward.
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError>;
 }
@@ -0,0 +1,134 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use cosmwasm_std::Storage;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Hasher, MerkleProof, MerkleTree, MerkleTreeError};
+
+use super::SparseMerkleTree;
+
+/// Independent [SparseMerkleTree]s keyed by an id, e.g. one tree per epoch.
+/// The namespaces of each tree are derived from the `namespace` and the id as `{namespace}:{id}:{name}`.
+pub struct MerkleForest<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+    H: Hasher<L>,
+> {
+    _l: PhantomData<L>,
+    _h: PhantomData<H>,
+    pub namespace: &'a str,
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>>
+    MerkleForest<'a, L, H>
+{
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            _l: PhantomData,
+            _h: PhantomData,
+            namespace,
+        }
+    }
+
+    /// Initialize the tree of `id` like [MerkleTree::init].
+    pub fn init(
+        &self,
+        storage: &mut dyn Storage,
+        id: u64,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.with_tree(id, |tree| tree.init(storage, level, default_leaf, hasher))
+    }
+
+    /// Insert the `leaf` into the tree of `id` like [MerkleTree::insert].
+    pub fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        id: u64,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        self.with_tree(id, |tree| tree.insert(storage, leaf, hasher))
+    }
+
+    /// Get the latest root of the tree of `id` like [MerkleTree::get_latest_root].
+    pub fn root(&self, storage: &dyn Storage, id: u64) -> Result<L, MerkleTreeError> {
+        self.with_tree(id, |tree| tree.get_latest_root(storage))
+    }
+
+    /// Check if the `root` is valid for the tree of `id` like [MerkleTree::is_valid_root].
+    pub fn is_valid_root(
+        &self,
+        storage: &dyn Storage,
+        id: u64,
+        root: &L,
+    ) -> Result<bool, MerkleTreeError> {
+        self.with_tree(id, |tree| tree.is_valid_root(storage, root))
+    }
+
+    /// Generate the proof of the leaf at `index` of the tree of `id` like [SparseMerkleTree::gen_proof].
+    pub fn gen_proof(
+        &self,
+        storage: &dyn Storage,
+        id: u64,
+        index: u64,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
+        self.with_tree(id, |tree| tree.gen_proof(storage, index, hasher))
+    }
+
+    /// Run `f` with the tree of `id`, the tree only borrows its namespaces for the call.
+    fn with_tree<R>(&self, id: u64, f: impl FnOnce(&SparseMerkleTree<L, H>) -> R) -> R {
+        let ns = |name: &str| format!("{}:{}:{}", self.namespace, id, name);
+        let (hashes, leafs, level, root) = (ns("hashes"), ns("leafs"), ns("level"), ns("root"));
+
+        f(&SparseMerkleTree::new(&hashes, &leafs, &level, &root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{test_utils::Blake2, Hasher};
+
+    use super::MerkleForest;
+
+    const FOREST: MerkleForest<Uint256, Blake2> = MerkleForest::new("forest");
+
+    #[test]
+    fn isolated_trees() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        FOREST.init(&mut storage, 1, 20, default_leaf, &Blake2)?;
+        FOREST.init(&mut storage, 2, 20, default_leaf, &Blake2)?;
+
+        let empty_root = FOREST.root(&storage, 2)?;
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        assert_eq!(FOREST.insert(&mut storage, 1, leaf, &Blake2)?.0, 0);
+        let (index, root) = FOREST.insert(&mut storage, 1, leaf, &Blake2)?;
+
+        assert_eq!(index, 1);
+        assert_eq!(FOREST.root(&storage, 1)?, root);
+        assert!(FOREST.is_valid_root(&storage, 1, &root)?);
+
+        // The other tree is untouched
+        assert_eq!(FOREST.root(&storage, 2)?, empty_root);
+        assert!(!FOREST.is_valid_root(&storage, 2, &root)?);
+        assert_eq!(FOREST.insert(&mut storage, 2, leaf, &Blake2)?.0, 0);
+        assert_ne!(FOREST.root(&storage, 2)?, root);
+
+        assert!(FOREST
+            .gen_proof(&storage, 1, 1, &Blake2)?
+            .verify(&root, &leaf, &Blake2)?);
+        assert!(FOREST.root(&storage, 3).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::Storage;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Hasher, MerkleProof, MerkleTree, MerkleTreeError};
+
+use super::{SparseMerkleTree, TreeNamespaces};
+
+/// Maximum level of a [GrowableMerkleTree] and of any tree initialized in storage, the leaf positions are `u64`.
+pub const MAX_GROWABLE_LEVEL: u8 = 64;
+
+/// Like [SparseMerkleTree] but the level grows by one instead of rejecting the insert once the tree is full.
+///
+/// Each growth doubles the capacity, the full tree becomes the left child of the new root and the new right half is empty.
+/// The root then has one more level, the same as a tree initialized with the new level holding the same leaves.
+/// The roots and proofs before the growth are of the shallower tree and do not verify against the later roots.
+pub struct GrowableMerkleTree<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+    H: Hasher<L>,
+> {
+    pub tree: SparseMerkleTree<'a, L, H>,
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>>
+    GrowableMerkleTree<'a, L, H>
+{
+    pub const fn new(
+        hashes_ns: &'a str,
+        leafs_ns: &'a str,
+        level_ns: &'a str,
+        root_ns: &'a str,
+    ) -> Self {
+        Self {
+            tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
+        }
+    }
+
+    /// Like [GrowableMerkleTree::new] but with the tree namespaces set by name.
+    pub const fn from_namespaces(tree: TreeNamespaces<'a>) -> Self {
+        Self {
+            tree: SparseMerkleTree::from_namespaces(tree),
+        }
+    }
+
+    /// Generate the proof of the leaf at `index` like [SparseMerkleTree::gen_proof].
+    pub fn gen_proof(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
+        self.tree.gen_proof(storage, index, hasher)
+    }
+
+    /// Grow the tree by one level if it is full, before the insert of `leaf`.
+    /// The insert is checked first, the tree is not grown for a leaf that is rejected anyway.
+    fn grow_if_full(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: &L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.check_insert(storage, leaf, hasher)?;
+        if !self.tree.is_full(storage)? {
+            return Ok(());
+        }
+
+        (self.tree.level(storage)? < MAX_GROWABLE_LEVEL)
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+
+        self.tree.grow_level(storage, hasher)
+    }
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>> MerkleTree<L, H>
+    for GrowableMerkleTree<'a, L, H>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.init(storage, level, default_leaf, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        self.tree.is_valid_root(storage, root)
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.roots_count(storage)
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert], growing the tree first if it is full.
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        self.grow_if_full(storage, &leaf, hasher)?;
+
+        self.tree.insert(storage, leaf, hasher)
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        self.grow_if_full(storage, &leaf, hasher)?;
+
+        self.tree.insert_returning_proof(storage, leaf, hasher)
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    /// Check if the tree is full at [MAX_GROWABLE_LEVEL], a full tree of a lower level still grows on insert.
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        Ok(self.tree.level(storage)? >= MAX_GROWABLE_LEVEL && self.tree.is_full(storage)?)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{
+        test_utils::{Blake2, NAMESPACES},
+        tree::SparseMerkleTree,
+        Hasher, HasherError, MerkleTree, MerkleTreeError,
+    };
+
+    use super::GrowableMerkleTree;
+
+    const TREE: GrowableMerkleTree<Uint256, Blake2> =
+        GrowableMerkleTree::from_namespaces(NAMESPACES);
+    const EXPECTED_TREE: SparseMerkleTree<Uint256, Blake2> = SparseMerkleTree::new(
+        "expected_hashes",
+        "expected_leafs",
+        "expected_level",
+        "expected_root",
+    );
+
+    #[test]
+    fn grow() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 1, default_leaf, &Blake2)?;
+        EXPECTED_TREE.init(&mut storage, 2, default_leaf, &Blake2)?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TREE.insert_from_iter(&mut storage, leafs[..2].to_vec(), &Blake2)?;
+
+        assert_eq!(TREE.level(&storage)?, 1);
+        assert!(TREE.tree.is_full(&storage)?);
+        assert!(!TREE.is_full(&storage)?);
+
+        // The third leaf grows the tree to level 2
+        let (index, root) = TREE.insert(&mut storage, leafs[2], &Blake2)?;
+        EXPECTED_TREE.insert_from_iter(&mut storage, leafs[..3].to_vec(), &Blake2)?;
+
+        assert_eq!(index, 2);
+        assert_eq!(TREE.level(&storage)?, 2);
+        assert_eq!(root, EXPECTED_TREE.get_latest_root(&storage)?);
+        assert_eq!(TREE.zeros(&storage)?, EXPECTED_TREE.zeros(&storage)?);
+        for index in 0..3 {
+            assert_eq!(
+                TREE.gen_proof(&storage, index, &Blake2)?,
+                EXPECTED_TREE.gen_proof(&storage, index, &Blake2)?
+            );
+        }
+
+        // The frontier keeps following the deeper tree
+        let (_, root) = TREE.insert(&mut storage, leafs[3], &Blake2)?;
+        EXPECTED_TREE.insert(&mut storage, leafs[3], &Blake2)?;
+
+        assert_eq!(root, EXPECTED_TREE.get_latest_root(&storage)?);
+
+        TREE.insert(&mut storage, leafs[4], &Blake2)?;
+
+        assert_eq!(TREE.level(&storage)?, 3);
+        assert_eq!(TREE.leaf_count(&storage)?, 5);
+        assert!(TREE.prove_and_verify(&storage, 4, &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn grow_from_level_zero() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 0, default_leaf, &Blake2)?;
+        EXPECTED_TREE.init(&mut storage, 2, default_leaf, &Blake2)?;
+
+        let leafs = (1..=3)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(TREE.get_latest_root(&storage)?, default_leaf);
+        assert_eq!(TREE.insert(&mut storage, leafs[0], &Blake2)?, (0, leafs[0]));
+        assert_eq!(TREE.level(&storage)?, 0);
+
+        // Each insert into the full tree grows it by one level
+        for leaf in &leafs[1..] {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+        EXPECTED_TREE.insert_from_iter(&mut storage, leafs.clone(), &Blake2)?;
+
+        assert_eq!(TREE.level(&storage)?, 2);
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            EXPECTED_TREE.get_latest_root(&storage)?
+        );
+        for index in 0..3 {
+            assert_eq!(
+                TREE.gen_proof(&storage, index, &Blake2)?,
+                EXPECTED_TREE.gen_proof(&storage, index, &Blake2)?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_leaf_does_not_grow() -> Result<(), Box<dyn Error>> {
+        #[derive(Clone, Debug)]
+        struct NonZero;
+
+        impl Hasher<Uint256> for NonZero {
+            fn hash_two(&self, left: &Uint256, right: &Uint256) -> Result<Uint256, HasherError> {
+                Blake2.hash_two(left, right)
+            }
+
+            fn validate_leaf(&self, leaf: &Uint256) -> Result<(), MerkleTreeError> {
+                (!leaf.is_zero())
+                    .then_some(())
+                    .ok_or_else(|| MerkleTreeError::InvalidLeaf("zero".to_string()))
+            }
+        }
+
+        const TREE: GrowableMerkleTree<Uint256, NonZero> =
+            GrowableMerkleTree::from_namespaces(NAMESPACES);
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 1, Uint256::zero(), &NonZero)?;
+        TREE.insert(&mut storage, Uint256::one(), &NonZero)?;
+        TREE.insert(&mut storage, Uint256::from_u128(2), &NonZero)?;
+        let root = TREE.get_latest_root(&storage)?;
+
+        // The rejected leaf leaves the full tree as is
+        assert!(matches!(
+            TREE.insert(&mut storage, Uint256::zero(), &NonZero),
+            Err(MerkleTreeError::InvalidLeaf(_))
+        ));
+        assert_eq!(TREE.level(&storage)?, 1);
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+
+        Ok(())
+    }
+}
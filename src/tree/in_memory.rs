@@ -0,0 +1,186 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{algorithm, Hasher, MerkleProof, MerkleTreeError};
+
+use super::{compute_zeros, SparseMerkleTree, MAX_GROWABLE_LEVEL};
+
+/// Storage-free sparse merkle tree, using the same algorithm as [SparseMerkleTree].
+#[derive(Clone, Debug)]
+pub struct InMemoryTree<L, H> {
+    _h: PhantomData<H>,
+    /// Latest left node of each level.
+    pub frontier: Vec<L>,
+    pub leafs: Vec<L>,
+    /// Level of the tree, the tree of level 0 holds a single leaf as its root.
+    pub level: u8,
+    pub root: Option<L>,
+    /// Hash of the empty subtree of each level.
+    pub zeros: Vec<L>,
+}
+
+impl<L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>> InMemoryTree<L, H> {
+    /// Create the empty tree like [MerkleTree::init](crate::MerkleTree::init).
+    /// Return [MerkleTreeError::InvalidLevel] if the `level` is above [MAX_GROWABLE_LEVEL].
+    pub fn new(level: u8, default_leaf: L, hasher: &H) -> Result<Self, MerkleTreeError> {
+        (level <= MAX_GROWABLE_LEVEL)
+            .then_some(())
+            .ok_or(MerkleTreeError::InvalidLevel)?;
+
+        let zeros = compute_zeros(level, default_leaf, hasher)?;
+
+        Ok(Self {
+            _h: PhantomData,
+            frontier: zeros.clone(),
+            leafs: vec![],
+            level,
+            root: None,
+            zeros,
+        })
+    }
+
+    /// Insert the `leaf` into the next index slot of the tree like [MerkleTree::insert](crate::MerkleTree::insert).
+    /// Return the leaf's index and the updated root.
+    pub fn insert(&mut self, leaf: L, hasher: &H) -> Result<(u64, L), MerkleTreeError> {
+        hasher.validate_leaf(&leaf)?;
+
+        let index = self.leafs.len() as u64;
+
+        algorithm::position_fits(index, self.level as usize)
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+
+        // The zeros and the frontier of level 0 still hold the default leaf, only the nodes below the root are hashed
+        let level = self.level as usize;
+        let root = SparseMerkleTree::<L, H>::append(
+            &mut self.frontier[..level],
+            &self.zeros[..level],
+            index,
+            leaf.clone(),
+            hasher,
+        )?;
+        self.leafs.push(leaf);
+        self.root = Some(root.clone());
+
+        Ok((index, root))
+    }
+
+    /// Get the latest root of the tree.
+    pub fn root(&self) -> L {
+        self.root
+            .clone()
+            .unwrap_or_else(|| self.zeros.last().unwrap().clone())
+    }
+
+    /// Generate the proof of the leaf at `index` against the latest root like [SparseMerkleTree::gen_proof].
+    pub fn gen_proof(&self, index: u64, hasher: &H) -> Result<MerkleProof<L>, MerkleTreeError> {
+        if index >= self.leafs.len() as u64 {
+            return Err(MerkleTreeError::LeafNotFound { index });
+        }
+
+        Ok(SparseMerkleTree::<L, H>::fold_proof(
+            self.leafs.clone(),
+            &self.zeros[..self.level as usize],
+            index,
+            hasher,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{test_utils::Blake2, tree::SparseMerkleTree, Hasher, MerkleTree, MerkleTreeError};
+
+    use super::InMemoryTree;
+
+    const TREE: SparseMerkleTree<Uint256, Blake2> =
+        SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+    #[test]
+    fn cross_check() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let mut tree = InMemoryTree::new(20, default_leaf, &Blake2)?;
+
+        assert_eq!(tree.root(), TREE.get_latest_root(&storage)?);
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+
+            assert_eq!(
+                tree.insert(leaf, &Blake2)?,
+                TREE.insert(&mut storage, leaf, &Blake2)?
+            );
+            assert_eq!(tree.root(), TREE.get_latest_root(&storage)?);
+        }
+
+        for index in 0..5 {
+            assert_eq!(
+                tree.gen_proof(index, &Blake2)?,
+                TREE.gen_proof(&storage, index, &Blake2)?
+            );
+        }
+        assert!(matches!(
+            tree.gen_proof(5, &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 5 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_level() -> Result<(), Box<dyn Error>> {
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        for level in [65, 128, 200] {
+            assert!(matches!(
+                InMemoryTree::new(level, default_leaf, &Blake2),
+                Err(MerkleTreeError::InvalidLevel)
+            ));
+        }
+
+        let mut tree = InMemoryTree::new(64, default_leaf, &Blake2)?;
+        assert_eq!(tree.insert(leaf, &Blake2)?.0, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn level_zero() -> Result<(), Box<dyn Error>> {
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        let mut tree = InMemoryTree::new(0, default_leaf, &Blake2)?;
+
+        assert_eq!(tree.root(), default_leaf);
+        assert_eq!(tree.insert(leaf, &Blake2)?, (0, leaf));
+        assert_eq!(tree.root(), leaf);
+        assert!(matches!(
+            tree.insert(leaf, &Blake2),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+
+        let proof = tree.gen_proof(0, &Blake2)?;
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&leaf, &leaf, &Blake2)?);
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 0, default_leaf, &Blake2)?;
+        TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(tree.root(), TREE.get_latest_root(&storage)?);
+        assert_eq!(proof, TREE.gen_proof(&storage, 0, &Blake2)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::{Attribute, Storage};
+use cw_storage_plus::Item;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Hasher, LeafBytes, MerkleProof, MerkleTree, MerkleTreeError, TreeMetrics};
+
+/// Wrapper of any [MerkleTree] counting the inserts and the root checks in its own storage.
+/// The counters are kept across [MerkleTree::reset] of the inner tree.
+/// [MerkleTree::is_valid_root] only gets the read-only storage and cannot save a count, use [InstrumentedTree::check_root] to count the root checks.
+pub struct InstrumentedTree<'a, T> {
+    pub tree: T,
+    /// Number of inserted leaves.
+    pub inserts: Item<'a, u64>,
+    /// Number of root checks done with [InstrumentedTree::check_root].
+    pub root_checks: Item<'a, u64>,
+}
+
+impl<'a, T> InstrumentedTree<'a, T> {
+    pub const fn new(tree: T, inserts_ns: &'a str, root_checks_ns: &'a str) -> Self {
+        Self {
+            tree,
+            inserts: Item::new(inserts_ns),
+            root_checks: Item::new(root_checks_ns),
+        }
+    }
+
+    /// Check if the `root` is valid like [MerkleTree::is_valid_root] and count the check.
+    /// [MerkleTree::is_valid_root] only has the read-only storage and is not counted.
+    pub fn check_root<L, H>(
+        &self,
+        storage: &mut dyn Storage,
+        root: &L,
+    ) -> Result<bool, MerkleTreeError>
+    where
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+        H: Hasher<L>,
+        T: MerkleTree<L, H>,
+    {
+        let root_checks = Self::next_count(storage, &self.root_checks)?;
+        let is_valid = self.tree.is_valid_root(storage, root)?;
+        self.root_checks.save(storage, &root_checks)?;
+
+        Ok(is_valid)
+    }
+
+    /// Get the counters of the tree.
+    pub fn metrics(&self, storage: &dyn Storage) -> Result<TreeMetrics, MerkleTreeError> {
+        Ok(TreeMetrics {
+            inserts: self.inserts.may_load(storage)?.unwrap_or_default(),
+            root_checks: self.root_checks.may_load(storage)?.unwrap_or_default(),
+        })
+    }
+
+    /// Get the `counter` after the next count, return [MerkleTreeError::Overflow] instead of wrapping.
+    fn next_count(storage: &dyn Storage, counter: &Item<'a, u64>) -> Result<u64, MerkleTreeError> {
+        counter
+            .may_load(storage)?
+            .unwrap_or_default()
+            .checked_add(1)
+            .ok_or(MerkleTreeError::Overflow)
+    }
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+        H: Hasher<L>,
+        T: MerkleTree<L, H>,
+    > MerkleTree<L, H> for InstrumentedTree<'a, T>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.init(storage, level, default_leaf, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        self.tree.is_valid_root(storage, root)
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.roots_count(storage)
+    }
+
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        let inserts = Self::next_count(storage, &self.inserts)?;
+        let inserted = self.tree.insert(storage, leaf, hasher)?;
+        self.inserts.save(storage, &inserts)?;
+
+        Ok(inserted)
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        let inserts = Self::next_count(storage, &self.inserts)?;
+        let inserted = self.tree.insert_returning_proof(storage, leaf, hasher)?;
+        self.inserts.save(storage, &inserts)?;
+
+        Ok(inserted)
+    }
+
+    fn insert_with_event(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, Vec<Attribute>), MerkleTreeError>
+    where
+        L: LeafBytes,
+    {
+        let inserts = Self::next_count(storage, &self.inserts)?;
+        let inserted = self.tree.insert_with_event(storage, leaf, hasher)?;
+        self.inserts.save(storage, &inserts)?;
+
+        Ok(inserted)
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{attr, testing::MockStorage};
+
+    use crate::{
+        leaf_to_hex,
+        test_utils::Blake2,
+        tree::{SparseMerkleTreeWithHistory, SparseMerkleTreeWithHistoryBounded},
+        MerkleTree, MerkleTreeError, TreeMetrics,
+    };
+
+    use super::InstrumentedTree;
+
+    const TREE: InstrumentedTree<SparseMerkleTreeWithHistory<Vec<u8>, Blake2>> =
+        InstrumentedTree::new(
+            SparseMerkleTreeWithHistory::new("hashes", "leafs", "level", "root", "root_history"),
+            "inserts",
+            "root_checks",
+        );
+
+    #[test]
+    fn metrics() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 2, vec![0; 32], &Blake2)?;
+
+        assert_eq!(
+            TREE.metrics(&storage)?,
+            TreeMetrics {
+                inserts: 0,
+                root_checks: 0,
+            }
+        );
+
+        let (_, root) = TREE.insert(&mut storage, vec![1; 32], &Blake2)?;
+        TREE.insert_from_iter(&mut storage, [vec![2; 32], vec![3; 32]], &Blake2)?;
+
+        assert!(TREE.check_root(&mut storage, &root)?);
+        assert!(!TREE.check_root(&mut storage, &vec![4; 32])?);
+        assert!(TREE.is_valid_root(&storage, &root)?);
+
+        TREE.insert(&mut storage, vec![4; 32], &Blake2)?;
+
+        // Failed inserts are not counted
+        assert!(matches!(
+            TREE.insert(&mut storage, vec![5; 32], &Blake2),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+        assert_eq!(
+            TREE.metrics(&storage)?,
+            TreeMetrics {
+                inserts: 4,
+                root_checks: 2,
+            }
+        );
+
+        TREE.reset(&mut storage)?;
+
+        assert_eq!(TREE.metrics(&storage)?.inserts, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_with_event() -> Result<(), Box<dyn Error>> {
+        const BOUNDED_TREE: InstrumentedTree<
+            SparseMerkleTreeWithHistoryBounded<Vec<u8>, Blake2, 1>,
+        > = InstrumentedTree::new(
+            SparseMerkleTreeWithHistoryBounded::new(
+                "hashes",
+                "leafs",
+                "level",
+                "root",
+                "root_history",
+                "root_index",
+                "history_index",
+            ),
+            "inserts",
+            "root_checks",
+        );
+
+        let mut storage = MockStorage::new();
+
+        BOUNDED_TREE.init(&mut storage, 2, vec![0; 32], &Blake2)?;
+
+        let (_, first_root, _) =
+            BOUNDED_TREE.insert_with_event(&mut storage, vec![1; 32], &Blake2)?;
+        let (index, root, attributes) =
+            BOUNDED_TREE.insert_with_event(&mut storage, vec![2; 32], &Blake2)?;
+
+        // The evicted root of the inner tree is kept
+        assert_eq!(
+            attributes,
+            vec![
+                attr("leaf_index", index.to_string()),
+                attr("root", leaf_to_hex(&root)),
+                attr("evicted_root", leaf_to_hex(&first_root)),
+            ]
+        );
+        assert_eq!(BOUNDED_TREE.metrics(&storage)?.inserts, 2);
+
+        Ok(())
+    }
+}
@@ -1,7 +1,31 @@
+mod forest;
+mod growable;
+mod in_memory;
+mod instrumented;
+mod namespaces;
+mod root_history;
+mod sorted;
 mod sparse;
+mod sparse_full_node;
 mod sparse_history;
 mod sparse_history_bounded;
+mod sparse_history_configurable;
+mod sparse_leaf_index;
+mod sparse_metadata;
+mod sparse_pair_order;
 
-pub use sparse::SparseMerkleTree;
+pub use forest::MerkleForest;
+pub use growable::{GrowableMerkleTree, MAX_GROWABLE_LEVEL};
+pub use in_memory::InMemoryTree;
+pub use instrumented::InstrumentedTree;
+pub use namespaces::{BoundedHistoryNamespaces, KeyedItem, KeyedMap, TreeNamespaces};
+pub use root_history::RootHistory;
+pub use sorted::SortedMerkleTree;
+pub use sparse::{compute_zeros, root_from_frontier, verify_append, Padding, SparseMerkleTree};
+pub use sparse_full_node::SparseMerkleTreeFullNode;
 pub use sparse_history::SparseMerkleTreeWithHistory;
 pub use sparse_history_bounded::SparseMerkleTreeWithHistoryBounded;
+pub use sparse_history_configurable::SparseMerkleTreeWithHistoryConfigurable;
+pub use sparse_leaf_index::SparseMerkleTreeWithLeafIndex;
+pub use sparse_metadata::SparseMerkleTreeWithMetadata;
+pub use sparse_pair_order::{PairOrder, PairOrderHasher, SparseMerkleTreeWithPairOrder};
@@ -0,0 +1,242 @@
+use cosmwasm_std::{Order, StdResult, Storage};
+use cw_storage_plus::{Bound, KeyDeserialize, Map, PrimaryKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Storage namespaces of [SparseMerkleTree](super::SparseMerkleTree), set by name to avoid swapping positional arguments.
+/// [TreeNamespaces::new] keeps the zeros under the level namespace and the auxiliary state under the root namespace,
+/// both keyed apart from the level and the root themselves, so the four namespaces of a tree are enough.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeNamespaces<'a> {
+    pub hashes: &'a str,
+    pub leafs: &'a str,
+    pub level: &'a str,
+    pub root: &'a str,
+    /// Namespace of the zeros keyed by level.
+    pub zeros: &'a str,
+    /// Namespace of the auxiliary state like the start index, the config and the frozen flag, each keyed by its name.
+    pub meta: &'a str,
+}
+
+impl<'a> TreeNamespaces<'a> {
+    pub const fn new(hashes: &'a str, leafs: &'a str, level: &'a str, root: &'a str) -> Self {
+        Self {
+            hashes,
+            leafs,
+            level,
+            root,
+            zeros: level,
+            meta: root,
+        }
+    }
+}
+
+/// Single value stored under the `key` of a namespace shared with other values.
+/// Used like an [Item](cw_storage_plus::Item), it never collides with an item stored at the namespace itself.
+pub struct KeyedItem<'a, T> {
+    map: Map<'a, &'a str, T>,
+    key: &'a str,
+}
+
+impl<'a, T> KeyedItem<'a, T> {
+    pub const fn new(namespace: &'a str, key: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace),
+            key,
+        }
+    }
+}
+
+impl<'a, T: Serialize + DeserializeOwned> KeyedItem<'a, T> {
+    pub fn save(&self, storage: &mut dyn Storage, data: &T) -> StdResult<()> {
+        self.map.save(storage, self.key, data)
+    }
+
+    pub fn load(&self, storage: &dyn Storage) -> StdResult<T> {
+        self.map.load(storage, self.key)
+    }
+
+    pub fn may_load(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
+        self.map.may_load(storage, self.key)
+    }
+
+    pub fn remove(&self, storage: &mut dyn Storage) {
+        self.map.remove(storage, self.key)
+    }
+}
+
+/// Map stored under the `key` of a namespace shared with other values, the map counterpart of [KeyedItem].
+/// Its entries never collide with the keyed items of the same namespace.
+pub struct KeyedMap<'a, K, T> {
+    map: Map<'a, (&'a str, K), T>,
+    key: &'a str,
+}
+
+impl<'a, K, T> KeyedMap<'a, K, T> {
+    pub const fn new(namespace: &'a str, key: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace),
+            key,
+        }
+    }
+}
+
+impl<
+        'a,
+        K: PrimaryKey<'a> + KeyDeserialize<Output = K> + 'static,
+        T: Serialize + DeserializeOwned,
+    > KeyedMap<'a, K, T>
+{
+    pub fn save(&self, storage: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
+        self.map.save(storage, (self.key, k), data)
+    }
+
+    pub fn load(&self, storage: &dyn Storage, k: K) -> StdResult<T> {
+        self.map.load(storage, (self.key, k))
+    }
+
+    pub fn may_load(&self, storage: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        self.map.may_load(storage, (self.key, k))
+    }
+
+    pub fn has(&self, storage: &dyn Storage, k: K) -> bool {
+        self.map.has(storage, (self.key, k))
+    }
+
+    pub fn remove(&self, storage: &mut dyn Storage, k: K) {
+        self.map.remove(storage, (self.key, k))
+    }
+
+    pub fn range<'c>(
+        &self,
+        storage: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K, T)>> + 'c>
+    where
+        T: 'c,
+    {
+        self.map.prefix(self.key).range(storage, min, max, order)
+    }
+
+    /// Remove every entry of the map.
+    pub fn clear(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        let keys = self
+            .map
+            .prefix(self.key)
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for k in keys {
+            self.remove(storage, k);
+        }
+
+        Ok(())
+    }
+}
+
+/// Storage namespaces of the root history in [SparseMerkleTreeWithHistoryBounded](super::SparseMerkleTreeWithHistoryBounded).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundedHistoryNamespaces<'a> {
+    pub root_history: &'a str,
+    pub root_index: &'a str,
+    pub history_index: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{
+        test_utils::{Blake2, NAMESPACES},
+        tree::{SparseMerkleTree, SparseMerkleTreeWithHistory, SparseMerkleTreeWithHistoryBounded},
+        Hasher, MerkleTree,
+    };
+
+    use super::BoundedHistoryNamespaces;
+
+    const TREE: SparseMerkleTree<Vec<u8>, Blake2> = SparseMerkleTree::from_namespaces(NAMESPACES);
+    const HISTORY_TREE: SparseMerkleTreeWithHistory<Vec<u8>, Blake2> =
+        SparseMerkleTreeWithHistory::from_namespaces(NAMESPACES, "root_history");
+    const BOUNDED_TREE: SparseMerkleTreeWithHistoryBounded<Vec<u8>, Blake2, 5> =
+        SparseMerkleTreeWithHistoryBounded::from_namespaces(
+            NAMESPACES,
+            BoundedHistoryNamespaces {
+                root_history: "root_history",
+                root_index: "root_index",
+                history_index: "history_index",
+            },
+        );
+
+    fn insert_one<T: MerkleTree<Vec<u8>, Blake2>>(tree: &T) -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = Uint256::zero().to_be_bytes().to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        tree.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let (index, new_root) =
+            tree.insert(&mut storage, Blake2.hash_two(&one_vec, &one_vec)?, &Blake2)?;
+
+        assert_eq!(index, 0);
+        assert_eq!(
+            new_root,
+            [
+                144, 77, 181, 73, 235, 223, 13, 204, 30, 18, 199, 252, 182, 160, 89, 248, 240, 219,
+                173, 150, 189, 114, 165, 70, 40, 159, 110, 9, 165, 185, 17, 229
+            ]
+        );
+        assert!(tree.is_valid_root(&storage, &new_root)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_namespaces() -> Result<(), Box<dyn Error>> {
+        insert_one(&TREE)?;
+        insert_one(&HISTORY_TREE)?;
+        insert_one(&BOUNDED_TREE)?;
+
+        assert_eq!(TREE.root.as_slice(), b"root");
+        assert_eq!(HISTORY_TREE.root_history.namespace(), b"root_history");
+        assert_eq!(
+            BOUNDED_TREE.history.history_index.as_slice(),
+            b"history_index"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn derived_namespaces() -> Result<(), Box<dyn Error>> {
+        const OTHER_TREE: SparseMerkleTree<Vec<u8>, Blake2> =
+            SparseMerkleTree::new("other_hashes", "other_leafs", "other_level", "other_root");
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 20, vec![0; 32], &Blake2)?;
+        OTHER_TREE.init(&mut storage, 2, vec![1; 32], &Blake2)?;
+        TREE.freeze(&mut storage)?;
+
+        // The zeros and the auxiliary state of each tree stay apart, also from its level and root
+        assert_eq!(TREE.level(&storage)?, 20);
+        assert_eq!(TREE.default_leaf(&storage)?, vec![0; 32]);
+        assert_eq!(OTHER_TREE.config(&storage)?.level, 2);
+        assert_eq!(OTHER_TREE.zeros(&storage)?[0], vec![1; 32]);
+        assert!(!OTHER_TREE.is_frozen(&storage)?);
+
+        OTHER_TREE.insert(&mut storage, vec![2; 32], &Blake2)?;
+
+        assert!(OTHER_TREE.verify_integrity(&storage, &Blake2)?);
+        assert!(TREE.verify_integrity(&storage, &Blake2)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+use cosmwasm_std::{Empty, Order, StdResult, Storage};
+use cw_storage_plus::{Item, Map, PrimaryKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::MerkleTreeError;
+
+use super::{BoundedHistoryNamespaces, KeyedItem, KeyedMap};
+
+/// Ring buffer of the latest roots, shared by [SparseMerkleTreeWithHistoryBounded](super::SparseMerkleTreeWithHistoryBounded)
+/// and [SparseMerkleTreeWithHistoryConfigurable](super::SparseMerkleTreeWithHistoryConfigurable) which pass their own history level.
+pub struct RootHistory<'a, L> {
+    pub history_index: Item<'a, u32>,
+    pub root_history: Map<'a, L, Empty>,
+    pub root_index: Map<'a, u32, L>,
+    /// Number of inserted leaves since initialization, unlike `history_index` it never wraps.
+    /// Keyed under the history index namespace, seeded from the history index for the histories saved before it is kept.
+    pub total_inserts: KeyedItem<'a, u64>,
+    /// Total inserts at the time the root of each history index is saved, keyed under the history index namespace.
+    /// The root history and the root index namespaces are ranged over and cannot hold other keys.
+    pub root_inserts: KeyedMap<'a, u32, u64>,
+    /// Check if the root can be evicted from the root history, set with [RootHistory::with_can_evict].
+    pub can_evict: fn(&dyn Storage, &L) -> Result<bool, MerkleTreeError>,
+}
+
+/// Allow every root to be evicted, the default of [RootHistory].
+fn evict_any<L>(_storage: &dyn Storage, _root: &L) -> Result<bool, MerkleTreeError> {
+    Ok(true)
+}
+
+impl<'a, L> RootHistory<'a, L> {
+    pub const fn new(
+        root_history_ns: &'a str,
+        root_index_ns: &'a str,
+        history_index_ns: &'a str,
+    ) -> Self {
+        Self {
+            history_index: Item::new(history_index_ns),
+            root_history: Map::new(root_history_ns),
+            root_index: Map::new(root_index_ns),
+            total_inserts: KeyedItem::new(history_index_ns, "total_inserts"),
+            root_inserts: KeyedMap::new(history_index_ns, "root_inserts"),
+            can_evict: evict_any::<L>,
+        }
+    }
+
+    /// Like [RootHistory::new] but with the namespaces set by name.
+    pub const fn from_namespaces(namespaces: BoundedHistoryNamespaces<'a>) -> Self {
+        Self::new(
+            namespaces.root_history,
+            namespaces.root_index,
+            namespaces.history_index,
+        )
+    }
+
+    /// Pin the roots still referenced, `can_evict` returns false for the roots to keep in the root history.
+    /// A pinned root keeps its slot and stays valid, the next root is saved into the next slot of an unpinned root instead.
+    pub const fn with_can_evict(
+        self,
+        can_evict: fn(&dyn Storage, &L) -> Result<bool, MerkleTreeError>,
+    ) -> Self {
+        Self { can_evict, ..self }
+    }
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + PartialEq + PrimaryKey<'a>> RootHistory<'a, L> {
+    /// Check if the `root` is retained in the root history.
+    pub fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> bool {
+        self.root_history.has(storage, root.clone())
+    }
+
+    /// Count the roots retained in the root history.
+    pub fn roots_count(&self, storage: &dyn Storage) -> u64 {
+        self.root_history
+            .keys_raw(storage, None, None, Order::Ascending)
+            .count() as u64
+    }
+
+    /// Count the occupied slots of the root history.
+    pub fn slots_count(&self, storage: &dyn Storage) -> u64 {
+        self.root_index
+            .keys_raw(storage, None, None, Order::Ascending)
+            .count() as u64
+    }
+
+    /// Get the number of inserted leaves since initialization.
+    /// A history saved before the counter is kept counts from its history index, exact unless the history already wrapped.
+    pub fn total_inserts(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        match self.total_inserts.may_load(storage)? {
+            Some(total_inserts) => Ok(total_inserts),
+            None => Ok(self.history_index.may_load(storage)?.unwrap_or_default() as u64),
+        }
+    }
+
+    /// Get the total inserts after the next insert, checked before anything is written.
+    pub fn next_total_inserts(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.total_inserts(storage)?
+            .checked_add(1)
+            .ok_or(MerkleTreeError::Overflow)
+    }
+
+    /// Get the oldest root retained in the root history of `history_level` slots, which is the next root to be evicted.
+    /// Return `None` if no root is saved yet.
+    pub fn oldest_valid_root(
+        &self,
+        storage: &dyn Storage,
+        history_level: u32,
+    ) -> Result<Option<L>, MerkleTreeError> {
+        let cur_idx = self.history_index.may_load(storage)?.unwrap_or_default() as u64;
+        let next_idx = ((cur_idx + 1) % history_level as u64) as u32;
+
+        // The next slot is only empty before the history wraps, the oldest root is then at the lowest slot
+        match self.root_index.may_load(storage, next_idx)? {
+            Some(root) => Ok(Some(root)),
+            None => Ok(self
+                .root_index
+                .range(storage, None, None, Order::Ascending)
+                .next()
+                .transpose()?
+                .map(|(_, root)| root)),
+        }
+    }
+
+    /// Check if the `root` is retained and saved at most `max_age` inserts ago, the latest root has the age of 0.
+    /// Pinned roots keep the age of when they are saved.
+    pub fn is_valid_root_within(
+        &self,
+        storage: &dyn Storage,
+        root: &L,
+        max_age: u64,
+    ) -> Result<bool, MerkleTreeError> {
+        let total_inserts = self.total_inserts(storage)?;
+
+        for item in self.root_index.range(storage, None, None, Order::Ascending) {
+            let (idx, saved_root) = item?;
+            if &saved_root != root {
+                continue;
+            }
+
+            let inserts = self.root_inserts_at(storage, idx)?;
+            if total_inserts.saturating_sub(inserts) <= max_age {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Find the next slot of the root history of `history_level` slots, skipping the slots of the pinned roots.
+    /// Return the slot and the root to evict from it, or [MerkleTreeError::HistoryPinned] if every root is pinned.
+    pub(super) fn next_slot(
+        &self,
+        storage: &dyn Storage,
+        history_level: u32,
+    ) -> Result<(u32, Option<L>), MerkleTreeError> {
+        let history_level = history_level as u64;
+        let cur_idx = self.history_index.may_load(storage)?.unwrap_or_default() as u64;
+
+        for offset in 1..=history_level {
+            let idx = ((cur_idx + offset) % history_level) as u32;
+            match self.root_index.may_load(storage, idx)? {
+                Some(root) if !(self.can_evict)(storage, &root)? => continue,
+                root => return Ok((idx, root)),
+            }
+        }
+
+        Err(MerkleTreeError::HistoryPinned)
+    }
+
+    /// Save the `total_inserts` counted before the insert is written.
+    pub(super) fn save_total_inserts(
+        &self,
+        storage: &mut dyn Storage,
+        total_inserts: u64,
+    ) -> Result<(), MerkleTreeError> {
+        self.seed(storage)?;
+        self.total_inserts.save(storage, &total_inserts)?;
+
+        Ok(())
+    }
+
+    /// Save the `root` into the `slot` found by [RootHistory::next_slot], return the evicted root if any.
+    pub(super) fn save(
+        &self,
+        storage: &mut dyn Storage,
+        (idx, evicted_root): (u32, Option<L>),
+        root: &L,
+    ) -> Result<Option<L>, MerkleTreeError> {
+        self.seed(storage)?;
+
+        // Remove old root
+        if let Some(evicted_root) = &evicted_root {
+            self.root_history.remove(storage, evicted_root.clone());
+        }
+
+        // Insert new root
+        self.root_history.save(storage, root.clone(), &Empty {})?;
+        self.root_index.save(storage, idx, root)?;
+        let total_inserts = self.total_inserts(storage)?;
+        self.root_inserts.save(storage, idx, &total_inserts)?;
+
+        // Update current index
+        self.history_index.save(storage, &idx)?;
+
+        Ok(evicted_root)
+    }
+
+    /// Get the total inserts at the time the root of the history index `idx` is saved.
+    /// Before the counters are seeded the roots were saved one per insert, the root at `idx` by the insert `idx`.
+    fn root_inserts_at(&self, storage: &dyn Storage, idx: u32) -> Result<u64, MerkleTreeError> {
+        if let Some(inserts) = self.root_inserts.may_load(storage, idx)? {
+            return Ok(inserts);
+        }
+
+        let cur_idx = self.history_index.may_load(storage)?.unwrap_or_default();
+        Ok(match idx <= cur_idx {
+            true => idx as u64,
+            false => 0,
+        })
+    }
+
+    /// Persist the counters of a history saved before they are kept, before the history index moves.
+    fn seed(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        if self.total_inserts.may_load(storage)?.is_some() {
+            return Ok(());
+        }
+
+        let slots = self
+            .root_index
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for idx in slots {
+            let inserts = self.root_inserts_at(storage, idx)?;
+            self.root_inserts.save(storage, idx, &inserts)?;
+        }
+        let total_inserts = self.total_inserts(storage)?;
+        self.total_inserts.save(storage, &total_inserts)?;
+
+        Ok(())
+    }
+
+    /// Copy the retained roots and the counters into `dst`.
+    pub(super) fn snapshot_to(
+        &self,
+        storage: &mut dyn Storage,
+        dst: &Self,
+    ) -> Result<(), MerkleTreeError> {
+        let roots = self
+            .root_index
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (idx, root) in roots {
+            dst.root_history.save(storage, root.clone(), &Empty {})?;
+            dst.root_index.save(storage, idx, &root)?;
+            if let Some(inserts) = self.root_inserts.may_load(storage, idx)? {
+                dst.root_inserts.save(storage, idx, &inserts)?;
+            }
+        }
+        if let Some(history_index) = self.history_index.may_load(storage)? {
+            dst.history_index.save(storage, &history_index)?;
+        }
+        if let Some(total_inserts) = self.total_inserts.may_load(storage)? {
+            dst.total_inserts.save(storage, &total_inserts)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove every retained root, the total inserts are kept.
+    pub(super) fn clear(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.seed(storage)?;
+        self.root_history.clear(storage);
+        self.root_index.clear(storage);
+        self.root_inserts.clear(storage)?;
+        self.history_index.remove(storage);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::Storage;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Hasher, MerkleProof, MerkleTree, MerkleTreeError};
+
+use super::{PairOrder, SparseMerkleTreeWithPairOrder, TreeNamespaces};
+
+/// Like [SparseMerkleTree](super::SparseMerkleTree) but always hashes the sorted pair of children.
+/// The proofs are verified with [MerkleProof::verify_sorted] without the direction bits.
+/// A missing right node is padded with the zero of its level, so only the roots of full trees match
+/// the libraries carrying the odd node up unhashed.
+pub struct SortedMerkleTree<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + Ord,
+    H: Hasher<L>,
+> {
+    pub tree: SparseMerkleTreeWithPairOrder<'a, L, H>,
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + Ord, H: Hasher<L>>
+    SortedMerkleTree<'a, L, H>
+{
+    pub const fn new(
+        hashes_ns: &'a str,
+        leafs_ns: &'a str,
+        level_ns: &'a str,
+        root_ns: &'a str,
+    ) -> Self {
+        Self {
+            tree: SparseMerkleTreeWithPairOrder::new(
+                hashes_ns,
+                leafs_ns,
+                level_ns,
+                root_ns,
+                PairOrder::Sorted,
+            ),
+        }
+    }
+
+    /// Like [SortedMerkleTree::new] but with the tree namespaces set by name.
+    pub const fn from_namespaces(tree: TreeNamespaces<'a>) -> Self {
+        Self {
+            tree: SparseMerkleTreeWithPairOrder::from_namespaces(tree, PairOrder::Sorted),
+        }
+    }
+
+    /// Generate the proof of the leaf at `index` like [SparseMerkleTreeWithPairOrder::gen_proof].
+    pub fn gen_proof(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
+        self.tree.gen_proof(storage, index, hasher)
+    }
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + Ord, H: Hasher<L>>
+    MerkleTree<L, H> for SortedMerkleTree<'a, L, H>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.init(storage, level, default_leaf, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        self.tree.is_valid_root(storage, root)
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.roots_count(storage)
+    }
+
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        self.tree.insert(storage, leaf, hasher)
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        self.tree.insert_returning_proof(storage, leaf, hasher)
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn check_inclusion(
+        &self,
+        storage: &dyn Storage,
+        leaf: &L,
+        proof: &MerkleProof<L>,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.check_inclusion(storage, leaf, proof, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::{leaf_from_hex, test_utils::Blake2, Hasher, MerkleTree};
+
+    use super::SortedMerkleTree;
+
+    const TREE: SortedMerkleTree<[u8; 32], Blake2> =
+        SortedMerkleTree::new("hashes", "leafs", "level", "root");
+
+    #[test]
+    fn sorted_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 2, [0; 32], &Blake2)?;
+
+        let leafs = [[3; 32], [1; 32], [4; 32], [2; 32]];
+        for leaf in leafs {
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        // Root folded by hand from the sorted pairs, each pair is hashed as (min, max)
+        let sorted_hash = |a: &[u8; 32], b: &[u8; 32]| match a <= b {
+            true => Blake2.hash_two(a, b),
+            false => Blake2.hash_two(b, a),
+        };
+        let left = sorted_hash(&[3; 32], &[1; 32])?;
+        let right = sorted_hash(&[4; 32], &[2; 32])?;
+        let root = sorted_hash(&left, &right)?;
+
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+
+        // Fixed vector computed outside the crate with Node's blake2b512 truncated to 32 bytes,
+        // pairing the nodes like merkletreejs with `sortPairs`
+        assert_eq!(
+            root,
+            leaf_from_hex::<[u8; 32]>(
+                "ab2f685c2b8d858685b381453b55eb6e00bf812b78471fcc65b27671c656b5bb"
+            )?
+        );
+
+        for (index, leaf) in leafs.iter().enumerate() {
+            let proof = TREE.gen_proof(&storage, index as u64, &Blake2)?;
+
+            assert!(proof.verify_sorted(&root, leaf, &Blake2)?);
+            assert!(!proof.verify_sorted(&root, &[5; 32], &Blake2)?);
+        }
+
+        // The missing right node is padded with the zero of its level instead of carried up unhashed,
+        // so the root of a partial tree differs from the libraries promoting the odd node.
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 2, [0; 32], &Blake2)?;
+        for leaf in &leafs[..3] {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let padded = sorted_hash(&[4; 32], &[0; 32])?;
+
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            sorted_hash(&left, &padded)?
+        );
+        assert_ne!(
+            TREE.get_latest_root(&storage)?,
+            sorted_hash(&left, &[4; 32])?
+        );
+
+        Ok(())
+    }
+}
@@ -1,53 +1,293 @@
-use std::{fmt::Debug, marker::PhantomData};
+use std::{collections::BTreeSet, fmt::Debug, marker::PhantomData};
 
-use cosmwasm_std::{Order, Storage};
-use cw_storage_plus::{Item, Map};
-use serde::{de::DeserializeOwned, Serialize};
+use cosmwasm_std::{from_slice, to_vec, Order, StdResult, Storage};
+use cw_storage_plus::{Bound, Item, Map};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::{Hasher, MerkleTree, MerkleTreeError};
+use crate::{
+    algorithm, Frontier, Hasher, HasherError, LeafBytes, LeafIndex, MerkleProof, MerkleTree,
+    MerkleTreeError, MultiProof, StorageStats, TreeConfig, TreeState,
+};
+
+use super::{KeyedItem, TreeNamespaces, MAX_GROWABLE_LEVEL};
+
+/// Padding of the empty slots of the tree, the empty right sibling of a node is the padding of its level.
+///
+/// A tree padded with a fixed sentinel has a different root than a tree padded with the recursive zeros
+/// for the same leaves. The proofs still carry the padding as siblings and are verified with [MerkleProof::verify],
+/// but an external tree or verifier must pad the same way to reproduce the roots.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Padding {
+    /// Pad with the hash of the empty subtree of each level, starting from the default leaf.
+    #[default]
+    RecursiveZero,
+    /// Pad every level with the default leaf itself.
+    FixedSentinel,
+}
 
 /// Normal sparse merkle tree with customizable tree level and default leaf.
+/// The leaves are stored by the `I` integer key, `u64` by default.
 pub struct SparseMerkleTree<
     'a,
     L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
     H: Hasher<L>,
+    I: LeafIndex = u64,
 > {
     _l: PhantomData<L>,
     _h: PhantomData<H>,
-    pub hashes: Item<'a, (Vec<L>, Vec<L>)>,
-    pub leafs: Map<'a, u64, L>,
+    /// Latest left node keyed by level, the only part mutated on insert.
+    pub hashes: Map<'a, u8, L>,
+    pub leafs: Map<'a, I, L>,
     pub level: Item<'a, u8>,
     pub root: Item<'a, L>,
+    /// Hash of the empty subtree keyed by level, only written on init.
+    pub zeros: Map<'a, u8, L>,
+    /// Index of the first leaf, the leaf's position in the tree is its index minus this offset.
+    pub start_index: KeyedItem<'a, u64>,
+    /// Maximum size of a leaf in bytes, unbounded if not set.
+    pub max_leaf_bytes: KeyedItem<'a, u64>,
+    /// Padding of the empty slots, [Padding::RecursiveZero] if not set.
+    pub padding: KeyedItem<'a, Padding>,
+    /// Number of leaves inserted before the tree is resumed from a frontier, they are not stored.
+    pub skipped_leaves: KeyedItem<'a, u64>,
+    /// Configuration of the tree written on init, see [TreeConfig].
+    pub config: KeyedItem<'a, TreeConfig<L>>,
+    /// Set by [SparseMerkleTree::freeze], the tree rejects the inserts afterward.
+    pub frozen: KeyedItem<'a, bool>,
+    /// Called for each inserted leaf, set with [SparseMerkleTree::with_on_insert].
+    pub on_insert: fn(&mut dyn Storage, u64, &L) -> Result<(), MerkleTreeError>,
+    /// Measure the leaf in bytes against `max_leaf_bytes`, set with [SparseMerkleTree::with_leaf_bytes].
+    pub leaf_size: Option<fn(&L) -> usize>,
+}
+
+/// Measure the leaf by its [LeafBytes] representation.
+fn leaf_bytes_len<L: LeafBytes>(leaf: &L) -> usize {
+    leaf.leaf_bytes().len()
+}
+
+/// Do nothing on insert, the default of [SparseMerkleTree].
+fn ignore_insert<L>(
+    _storage: &mut dyn Storage,
+    _index: u64,
+    _leaf: &L,
+) -> Result<(), MerkleTreeError> {
+    Ok(())
+}
+
+/// Insert computed by [SparseMerkleTree::prepare_insert] without writing the storage.
+pub(crate) struct PreparedInsert<L> {
+    pub index: u64,
+    pub leaf: L,
+    pub root: L,
+    /// Proof of the leaf against the root, its index is the position of the leaf.
+    pub proof: MerkleProof<L>,
+    /// Node on the path of the leaf at each level below the root, starting from the leaf itself.
+    pub path: Vec<L>,
 }
 
-impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>>
-    SparseMerkleTree<'a, L, H>
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+        H: Hasher<L>,
+        I: LeafIndex,
+    > SparseMerkleTree<'a, L, H, I>
 {
+    /// Create the tree with the zeros and the auxiliary state derived from the four namespaces, see [TreeNamespaces::new].
     pub const fn new(
         hashes_ns: &'a str,
         leafs_ns: &'a str,
         level_ns: &'a str,
         root_ns: &'a str,
     ) -> Self {
+        Self::from_namespaces(TreeNamespaces::new(hashes_ns, leafs_ns, level_ns, root_ns))
+    }
+
+    /// Like [SparseMerkleTree::new] but with the namespaces set by name.
+    pub const fn from_namespaces(namespaces: TreeNamespaces<'a>) -> Self {
         Self {
             _l: PhantomData,
             _h: PhantomData,
-            hashes: Item::new(hashes_ns),
-            leafs: Map::new(leafs_ns),
-            level: Item::new(level_ns),
-            root: Item::new(root_ns),
+            hashes: Map::new(namespaces.hashes),
+            leafs: Map::new(namespaces.leafs),
+            level: Item::new(namespaces.level),
+            root: Item::new(namespaces.root),
+            zeros: Map::new(namespaces.zeros),
+            start_index: KeyedItem::new(namespaces.meta, "start_index"),
+            max_leaf_bytes: KeyedItem::new(namespaces.meta, "max_leaf_bytes"),
+            padding: KeyedItem::new(namespaces.meta, "padding"),
+            skipped_leaves: KeyedItem::new(namespaces.meta, "skipped_leaves"),
+            config: KeyedItem::new(namespaces.meta, "config"),
+            frozen: KeyedItem::new(namespaces.meta, "frozen"),
+            on_insert: ignore_insert::<L>,
+            leaf_size: None,
         }
     }
-}
 
-impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>> MerkleTree<L, H>
-    for SparseMerkleTree<'a, L, H>
-{
-    fn init(
+    /// Keep the auxiliary state like a nullifier set in sync with the tree, `on_insert` is called with the index of each inserted leaf.
+    /// It is called after the path of the leaf is hashed and before anything is saved, an error rejects the insert.
+    pub const fn with_on_insert(
+        self,
+        on_insert: fn(&mut dyn Storage, u64, &L) -> Result<(), MerkleTreeError>,
+    ) -> Self {
+        Self { on_insert, ..self }
+    }
+
+    /// Measure the leaves by their [LeafBytes] representation, required to limit the leaf size with `max_leaf_bytes`.
+    pub const fn with_leaf_bytes(self) -> Self
+    where
+        L: LeafBytes,
+    {
+        Self {
+            leaf_size: Some(leaf_bytes_len::<L>),
+            ..self
+        }
+    }
+
+    /// Move the frontier and the zeros of a tree stored by the earlier versions into their own maps.
+    /// The earlier versions store both in a single `(frontier, zeros)` item at the hashes namespace.
+    /// Return `false` if there is nothing to migrate, meant to be called in the `migrate` entry point.
+    pub fn migrate_legacy_hashes(
+        &self,
+        storage: &mut dyn Storage,
+    ) -> Result<bool, MerkleTreeError> {
+        let Some(legacy) = storage.get(self.hashes.namespace()) else {
+            return Ok(false);
+        };
+        let (frontier, zeros) = from_slice::<(Vec<L>, Vec<L>)>(&legacy)?;
+
+        Self::save_levels(storage, &self.hashes, &frontier)?;
+        Self::save_levels(storage, &self.zeros, &zeros)?;
+        storage.remove(self.hashes.namespace());
+
+        Ok(true)
+    }
+
+    /// Like [SparseMerkleTree::from_namespaces] but validate the existing tree, e.g. one set up by a prior code version.
+    /// The level, the frontier, the zeros and the root are checked against each other by replaying the stored leaves.
+    /// Return [MerkleTreeError::NotInitialized] if the level is not stored and [MerkleTreeError::StorageCorruption] on any mismatch.
+    pub fn try_from_storage(
+        storage: &dyn Storage,
+        namespaces: TreeNamespaces<'a>,
+        hasher: &H,
+    ) -> Result<Self, MerkleTreeError> {
+        let tree = Self::from_namespaces(namespaces);
+        let corrupted = |reason: &str| MerkleTreeError::StorageCorruption(reason.to_string());
+
+        let level = tree
+            .level
+            .may_load(storage)?
+            .ok_or(MerkleTreeError::NotInitialized)?;
+        tree.check_levels(storage, level)?;
+        if let Some(config) = tree.config.may_load(storage)? {
+            (config.level == level)
+                .then_some(())
+                .ok_or_else(|| corrupted("config mismatches level"))?;
+        }
+
+        let is_consistent = match tree.skipped_leaves.may_load(storage)? {
+            None => tree.verify_integrity(storage, hasher)?,
+            // The skipped leaves can not be replayed, check the root against the frontier instead
+            Some(_) => {
+                let Frontier {
+                    frontier,
+                    zeros,
+                    next_index,
+                } = tree.frontier(storage)?;
+
+                match algorithm::root_from_frontier(
+                    &frontier,
+                    &zeros,
+                    next_index,
+                    |left, right| hasher.hash_two(left, right),
+                )? {
+                    Some(root) => tree.root.may_load(storage)? == Some(root),
+                    // The frontier of a full tree does not determine its root
+                    None => true,
+                }
+            }
+        };
+        is_consistent
+            .then_some(tree)
+            .ok_or_else(|| corrupted("root mismatches the frontier"))
+    }
+
+    /// Get up to `limit` inserted leaves with their index, starting from `start` index inclusively.
+    pub fn leaves_in_range(
+        &self,
+        storage: &dyn Storage,
+        start: Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<(u64, L)>, MerkleTreeError> {
+        let start = match start {
+            Some(start) => match I::from_index(start) {
+                Some(key) => Some(Bound::inclusive(key)),
+                None => return Ok(vec![]),
+            },
+            None => None,
+        };
+
+        Ok(self
+            .leafs
+            .range(storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|e| e.map(|(key, leaf)| (key.into_index(), leaf)))
+            .collect::<StdResult<_>>()?)
+    }
+
+    /// Get the index the next inserted leaf will be at.
+    /// Return [MerkleTreeError::ExceedMaxLeaf] if the tree is full.
+    pub fn next_index(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        let level = self.level.load(storage)?;
+        let index = self.next_leaf_index(storage)?;
+        let position = index - self.first_index(storage)?;
+
+        algorithm::position_fits(position, level as usize)
+            .then_some(index)
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)
+    }
+
+    /// Get the index of the most recently inserted leaf, return `None` if no leaf is inserted yet.
+    pub fn latest_index(&self, storage: &dyn Storage) -> Result<Option<u64>, MerkleTreeError> {
+        Ok(self
+            .leafs
+            .keys(storage, None, None, Order::Descending)
+            .next()
+            .transpose()?
+            .map(LeafIndex::into_index))
+    }
+
+    /// Get the saved root of the tree, return `None` if no root is saved yet.
+    /// Unlike [MerkleTree::get_latest_root], the root of the empty tree is never returned in its place.
+    pub fn current_root(&self, storage: &dyn Storage) -> Result<Option<L>, MerkleTreeError> {
+        Ok(self.root.may_load(storage)?)
+    }
+
+    /// Initialize the tree like [MerkleTree::init] but reject the leaves larger than `max_leaf_bytes`.
+    /// The leaves are measured by their bytes, the tree must be built with [SparseMerkleTree::with_leaf_bytes].
+    pub fn init_with_max_leaf_bytes(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        max_leaf_bytes: u64,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.leaf_size()?;
+        self.init(storage, level, default_leaf, hasher)?;
+        self.max_leaf_bytes.save(storage, &max_leaf_bytes)?;
+
+        Ok(())
+    }
+
+    /// Initialize the tree like [MerkleTree::init] but pad the empty slots with the `padding`.
+    /// With [Padding::FixedSentinel] the `default_leaf` is the sentinel of every level.
+    pub fn init_with_padding(
         &self,
         storage: &mut dyn Storage,
         level: u8,
         default_leaf: L,
+        padding: Padding,
         hasher: &H,
     ) -> Result<(), MerkleTreeError> {
         self.level
@@ -55,170 +295,3480 @@ impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<
             .is_none()
             .then_some(())
             .ok_or(MerkleTreeError::AlreadyInit)?;
+        Self::check_level(level)?;
 
         self.level.save(storage, &level)?;
+        self.padding.save(storage, &padding)?;
+        self.save_config(storage, level, default_leaf.clone(), hasher.id())?;
 
-        let mut hashes = vec![default_leaf];
+        let hashes = Self::compute_padding(level, default_leaf, padding, hasher)?;
 
-        for i in 1..level as usize {
-            let latest = &hashes[i - 1];
-            hashes.push(hasher.hash_two(latest, latest)?);
-        }
+        Self::save_levels(storage, &self.hashes, &hashes)?;
+        Self::save_levels(storage, &self.zeros, &hashes)?;
 
-        self.hashes.save(storage, &(hashes.clone(), hashes))?;
+        Ok(())
+    }
+
+    /// Initialize the tree like [MerkleTree::init] but the first inserted leaf is at `start_index`.
+    /// The capacity of the tree is counted from `start_index`.
+    pub fn init_with_start_index(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        start_index: u64,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.level
+            .may_load(storage)?
+            .is_none()
+            .then_some(())
+            .ok_or(MerkleTreeError::AlreadyInit)?;
+        Self::check_level(level)?;
+
+        self.level.save(storage, &level)?;
+        self.start_index.save(storage, &start_index)?;
+        self.save_config(storage, level, default_leaf.clone(), hasher.id())?;
+
+        let hashes = compute_zeros(level, default_leaf, hasher)?;
+
+        Self::save_levels(storage, &self.hashes, &hashes)?;
+        Self::save_levels(storage, &self.zeros, &hashes)?;
 
         Ok(())
     }
 
-    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
-        Ok(self.root.may_load(storage)?.as_ref() == Some(root))
+    /// Initialize the tree from the `frontier` and `zeros` of a tree holding `next_index` leaves, like the [Frontier] export.
+    /// The leaves before `next_index` are not stored, the next inserted leaf continues the tree at `next_index`.
+    /// Only the lengths are validated, the `root` is trusted to be the latest root of the exported tree.
+    /// Methods replaying the leaves like [SparseMerkleTree::gen_proof] return [MerkleTreeError::LeafNotFound] afterward.
+    pub fn init_from_frontier(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        zeros: Vec<L>,
+        frontier: Vec<L>,
+        next_index: u64,
+        root: L,
+    ) -> Result<(), MerkleTreeError> {
+        self.level
+            .may_load(storage)?
+            .is_none()
+            .then_some(())
+            .ok_or(MerkleTreeError::AlreadyInit)?;
+        Self::check_level(level)?;
+
+        let invalid = |reason: &str| MerkleTreeError::InvalidState(reason.to_string());
+
+        (zeros.len() == (level as usize).max(1))
+            .then_some(())
+            .ok_or_else(|| invalid("zeros length mismatches level"))?;
+        (frontier.len() == zeros.len())
+            .then_some(())
+            .ok_or_else(|| invalid("frontier length mismatches level"))?;
+        algorithm::count_fits(next_index, level as usize)
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+        (next_index > 0 || zeros.last() == Some(&root))
+            .then_some(())
+            .ok_or_else(|| invalid("root mismatch"))?;
+
+        self.level.save(storage, &level)?;
+        self.save_config(storage, level, zeros[0].clone(), None)?;
+        Self::save_levels(storage, &self.hashes, &frontier)?;
+        Self::save_levels(storage, &self.zeros, &zeros)?;
+        if next_index > 0 {
+            self.skipped_leaves.save(storage, &next_index)?;
+            self.root.save(storage, &root)?;
+        }
+
+        Ok(())
     }
 
-    fn insert(
+    /// Initialize the tree like [MerkleTree::init] but succeed if it is already initialized with the same config.
+    /// Return [MerkleTreeError::ConfigMismatch] if the stored [TreeConfig] differs, the hasher is only compared if it is stored,
+    /// or if the tree is initialized with a start index, a maximum leaf size or a padding.
+    pub fn init_idempotent(
         &self,
         storage: &mut dyn Storage,
-        leaf: L,
+        level: u8,
+        default_leaf: L,
         hasher: &H,
-    ) -> Result<(u64, L), MerkleTreeError> {
+    ) -> Result<(), MerkleTreeError> {
+        match self.level.may_load(storage)? {
+            None => self.init(storage, level, default_leaf, hasher),
+            Some(_) => {
+                let config = self.config(storage)?;
+                let expected = TreeConfig {
+                    level,
+                    default_leaf,
+                    arity: 2,
+                    hasher: config.hasher.as_ref().and(hasher.id()),
+                };
+
+                (config == expected
+                    && self.first_index(storage)? == 0
+                    && self.max_leaf_bytes.may_load(storage)?.is_none()
+                    && self.padding.may_load(storage)?.unwrap_or_default()
+                        == Padding::RecursiveZero)
+                    .then_some(())
+                    .ok_or(MerkleTreeError::ConfigMismatch)
+            }
+        }
+    }
+
+    /// Generate the proof of the leaf at `index` against the latest root.
+    /// The proof is computed by folding all inserted leaves.
+    /// Return [MerkleTreeError::LeafNotFound] if the leaf at `index` is not inserted.
+    pub fn gen_proof(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
         let level = self.level.load(storage)?;
-        let index = {
-            self.leafs
-                .keys(storage, None, None, Order::Descending)
-                .next()
-                .transpose()?
-                .map(|e| e + 1)
-                .unwrap_or_default()
-        };
+        let (zeros, _) = self.load_path(storage, level)?;
+        if !self.has_leaf(storage, index) {
+            return Err(MerkleTreeError::LeafNotFound { index });
+        }
 
-        (index < 2u64.pow(level as u32))
-            .then_some(())
-            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+        let position = index - self.first_index(storage)?;
 
-        self.leafs.save(storage, index, &leaf)?;
+        Ok(Self::fold_proof(
+            self.load_leafs(storage)?,
+            &zeros,
+            position,
+            hasher,
+        )?)
+    }
 
-        let (mut hashes, zeros) = self.hashes.load(storage)?;
-        let mut cur_hash = leaf;
-        let mut cur_idx = index;
+    /// Generate the proof of the leaf at `index` against the past `root` by replaying the stored leaves.
+    /// The proof is computed by folding the leaves inserted up to the insert that resulted in the `root`.
+    /// Return [MerkleTreeError::RootNotFound] if no insert resulted in the `root`,
+    /// or [MerkleTreeError::LeafNotFound] if the leaf at `index` is inserted after it.
+    pub fn gen_proof_at_root(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        root: &L,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
+        let level = self.level.load(storage)?;
+        let (zeros, _) = self.load_path(storage, level)?;
+        if !self.has_leaf(storage, index) {
+            return Err(MerkleTreeError::LeafNotFound { index });
+        }
 
-        for i in 0..level as usize {
-            let (left, right) = match cur_idx % 2 == 0 {
-                true => {
-                    hashes[i] = cur_hash.clone();
-                    (&cur_hash, &zeros[i])
-                }
-                false => (&hashes[i], &cur_hash),
-            };
+        let position = index - self.first_index(storage)?;
+        let mut hashes = zeros.clone();
+        let mut leafs = vec![];
 
-            cur_hash = hasher.hash_two(left, right)?;
-            cur_idx /= 2;
+        for leaf in self.load_leafs(storage)? {
+            let cur_root = Self::append(
+                &mut hashes,
+                &zeros,
+                leafs.len() as u64,
+                leaf.clone(),
+                hasher,
+            )?;
+            leafs.push(leaf);
+
+            if &cur_root == root {
+                return match position < leafs.len() as u64 {
+                    true => Ok(Self::fold_proof(leafs, &zeros, position, hasher)?),
+                    false => Err(MerkleTreeError::LeafNotFound { index }),
+                };
+            }
         }
 
-        self.hashes.save(storage, &(hashes, zeros))?;
-        self.root.save(storage, &cur_hash)?;
+        Err(MerkleTreeError::RootNotFound)
+    }
+
+    /// Generate the proof of every inserted leaf against the latest root, ordered by the leaf's index.
+    /// The layers are folded once for all proofs, meant to be run off-chain or in a query.
+    pub fn all_proofs(
+        &self,
+        storage: &dyn Storage,
+        hasher: &H,
+    ) -> Result<Vec<(u64, MerkleProof<L>)>, MerkleTreeError> {
+        let (zeros, _) = self.load_path(storage, self.level.load(storage)?)?;
+        let first_index = self.first_index(storage)?;
+        let layer = self.load_leafs(storage)?;
+        let positions = (0..layer.len() as u64).collect::<Vec<_>>();
+        let proofs = Self::fold_proofs(layer, &zeros, &positions, hasher)?;
 
-        Ok((index, cur_hash))
+        Ok(proofs
+            .into_iter()
+            .map(|proof| (first_index + proof.index, proof))
+            .collect())
     }
 
-    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
-        Ok(self
-            .root
-            .may_load(storage)?
-            .unwrap_or(self.hashes.load(storage)?.1.last().unwrap().clone()))
+    /// Get up to `limit` inserted leaves from `start` index like [SparseMerkleTree::leaves_in_range], each with its proof against the latest root.
+    /// The layers are folded once for the whole page, like [SparseMerkleTree::all_proofs].
+    pub fn leaves_with_proofs(
+        &self,
+        storage: &dyn Storage,
+        start: Option<u64>,
+        limit: usize,
+        hasher: &H,
+    ) -> Result<Vec<(u64, L, MerkleProof<L>)>, MerkleTreeError> {
+        let page = self.leaves_in_range(storage, start, limit)?;
+        if page.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let (zeros, _) = self.load_path(storage, self.level.load(storage)?)?;
+        let first_index = self.first_index(storage)?;
+        let positions = page
+            .iter()
+            .map(|(index, _)| index - first_index)
+            .collect::<Vec<_>>();
+        let proofs = Self::fold_proofs(self.load_leafs(storage)?, &zeros, &positions, hasher)?;
+
+        Ok(page
+            .into_iter()
+            .zip(proofs)
+            .map(|((index, leaf), proof)| (index, leaf, proof))
+            .collect())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{error::Error, str::FromStr};
+    /// Generate the proof of all leaves at `indices` against the latest root.
+    /// The siblings shared between the leaves are only included once.
+    pub fn gen_multi_proof(
+        &self,
+        storage: &dyn Storage,
+        indices: &[u64],
+        hasher: &H,
+    ) -> Result<MultiProof<L>, MerkleTreeError> {
+        let level = self.level.load(storage)?;
+        let zeros = self.load_zeros(storage)?;
+        let mut layer = self.load_leafs(storage)?;
+        let first_index = self.first_index(storage)?;
+        let mut known = indices
+            .iter()
+            .map(|&index| match self.has_leaf(storage, index) {
+                true => Ok(index - first_index),
+                false => Err(MerkleTreeError::LeafNotFound { index }),
+            })
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        let proof_indices = known.iter().copied().collect();
+        let mut siblings = vec![];
 
-    use cosmwasm_std::{testing::MockStorage, Uint256};
+        for zero in zeros.iter().take(level as usize) {
+            for idx in known.iter() {
+                if !known.contains(&(idx ^ 1)) {
+                    siblings.push(layer.get((idx ^ 1) as usize).unwrap_or(zero).clone());
+                }
+            }
 
-    use crate::{test_utils::Blake2, Hasher, MerkleTree};
+            layer = Self::hash_layer(layer, zero, hasher)?;
+            known = known.into_iter().map(|idx| idx / 2).collect();
+        }
 
-    use super::SparseMerkleTree;
+        Ok(MultiProof {
+            indices: proof_indices,
+            level,
+            siblings,
+        })
+    }
 
-    const TREE: SparseMerkleTree<Uint256, Blake2> =
-        SparseMerkleTree::new("hashes", "leafs", "level", "zeros");
+    /// Check if the level, root and all leaves of the tree are equal to the `other` tree in `other_storage`.
+    pub fn state_eq(
+        &self,
+        storage: &dyn Storage,
+        other_storage: &dyn Storage,
+        other: &Self,
+    ) -> Result<bool, MerkleTreeError> {
+        if self.level.may_load(storage)? != other.level.may_load(other_storage)?
+            || self.root.may_load(storage)? != other.root.may_load(other_storage)?
+        {
+            return Ok(false);
+        }
 
-    #[test]
-    fn init() -> Result<(), Box<dyn Error>> {
-        let mut storage = MockStorage::new();
+        let mut leafs = self.leafs.range(storage, None, None, Order::Ascending);
+        let mut other_leafs = other
+            .leafs
+            .range(other_storage, None, None, Order::Ascending);
 
-        TREE.init(
-            &mut storage,
-            20,
-            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
-            &Blake2,
-        )?;
+        loop {
+            match (leafs.next().transpose()?, other_leafs.next().transpose()?) {
+                (None, None) => return Ok(true),
+                (leaf, other_leaf) if leaf != other_leaf => return Ok(false),
+                _ => continue,
+            }
+        }
+    }
 
-        assert_eq!(
-            TREE.get_latest_root(&storage)?,
-            Uint256::from_str(
-                "9249403463272353962338525770558810268347485650856754165003644360089862036530"
-            )?
-        );
+    /// Initialize the tree and insert all `leaves`, return the latest root.
+    pub fn init_with_leaves(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        leaves: Vec<L>,
+        hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        algorithm::count_fits(leaves.len() as u64, level as usize)
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
 
-        Ok(())
+        self.init(storage, level, default_leaf, hasher)?;
+
+        for leaf in leaves {
+            self.insert_no_root(storage, leaf, hasher)?;
+        }
+
+        self.commit_root(storage, hasher)
     }
 
-    #[test]
-    fn insert() -> Result<(), Box<dyn Error>> {
-        let mut storage = MockStorage::new();
+    /// Export the full state of the tree.
+    pub fn export_state(&self, storage: &dyn Storage) -> Result<TreeState<L>, MerkleTreeError> {
+        self.check_leafs_stored(storage)?;
 
-        TREE.init(
-            &mut storage,
-            20,
-            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
-            &Blake2,
-        )?;
+        Ok(TreeState {
+            level: self.level.load(storage)?,
+            frontier: self.load_hashes(storage)?,
+            zeros: self.load_zeros(storage)?,
+            start_index: self.first_index(storage)?,
+            max_leaf_bytes: self.max_leaf_bytes.may_load(storage)?,
+            padding: self.padding.may_load(storage)?.unwrap_or_default(),
+            frozen: self.is_frozen(storage)?,
+            root: self.root.may_load(storage)?,
+            leafs: self
+                .leafs
+                .range(storage, None, None, Order::Ascending)
+                .map(|e| e.map(|(key, leaf)| (key.into_index(), leaf)))
+                .collect::<StdResult<_>>()?,
+        })
+    }
 
-        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+    /// Export the frontier, zeros and next leaf position of the tree.
+    /// Much smaller than [SparseMerkleTree::export_state] but still able to append leaves off-chain.
+    pub fn frontier(&self, storage: &dyn Storage) -> Result<Frontier<L>, MerkleTreeError> {
+        Ok(Frontier {
+            frontier: self.load_hashes(storage)?,
+            zeros: self.load_zeros(storage)?,
+            next_index: self.leaf_count(storage)?,
+        })
+    }
 
-        let (index, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+    /// Compute the root after inserting the `extra_leaves` like [MerkleTree::insert_from_iter] without writing the storage.
+    /// Return [MerkleTreeError::ExceedMaxLeaf] if the leaves do not fit in the tree.
+    pub fn preview_root(
+        &self,
+        storage: &dyn Storage,
+        extra_leaves: &[L],
+        hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        let max_leaf_bytes = self.max_leaf_bytes.may_load(storage)?;
+        let (zeros, hashes) = self.load_path(storage, self.level.load(storage)?)?;
+        let mut frontier = Frontier {
+            frontier: hashes,
+            zeros,
+            next_index: self.leaf_count(storage)?,
+        };
+        let mut root = self.get_latest_root(storage)?;
 
-        assert_eq!(index, 0);
-        assert_eq!(
-            new_root,
-            Uint256::from_str(
-                "65270348628983318905821145914244198139930176154042934882987463098115489862117"
-            )?
-        );
-        assert_eq!(new_root, TREE.get_latest_root(&storage)?);
-        assert!(TREE.is_valid_root(&storage, &new_root)?);
+        for leaf in extra_leaves {
+            hasher.validate_leaf(leaf)?;
+            self.check_leaf_size(leaf, max_leaf_bytes)?;
+            root = frontier.append(leaf.clone(), hasher)?;
+        }
 
-        let (index, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+        Ok(root)
+    }
 
-        assert_eq!(index, 1);
-        assert_eq!(
-            new_root,
-            Uint256::from_str(
-                "31390868241958093005646829964058364480768696680064791450319134920411060649604"
-            )?
-        );
-        assert_eq!(new_root, TREE.get_latest_root(&storage)?);
-        assert!(TREE.is_valid_root(&storage, &new_root)?);
+    /// Count the stored leaves and estimate the storage size of the tree.
+    /// Every stored node is estimated to be as large as the serialized latest root.
+    pub fn storage_stats(&self, storage: &dyn Storage) -> Result<StorageStats, MerkleTreeError> {
+        let leaves = self.leaf_count(storage)?;
+        let level = self.level.load(storage)? as u64;
 
-        Ok(())
+        // Leaves, frontier, zeros and the root
+        Ok(StorageStats {
+            leaves,
+            history_roots: 0,
+            max_history_roots: Some(0),
+            estimated_bytes: (leaves + 2 * level + 1) * self.node_bytes(storage)?,
+        })
     }
 
-    #[test]
-    fn root_history() -> Result<(), Box<dyn Error>> {
-        let mut storage = MockStorage::new();
+    /// Size of the serialized latest root, used to estimate the size of any stored node.
+    pub(crate) fn node_bytes(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        Ok(to_vec(&self.get_latest_root(storage)?)?.len() as u64)
+    }
 
-        TREE.init(
-            &mut storage,
-            20,
-            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
-            &Blake2,
-        )?;
+    /// Import the exported `state` into the uninitialized tree.
+    /// The state is validated by replaying all leaves before anything is written.
+    pub fn import_state(
+        &self,
+        storage: &mut dyn Storage,
+        state: TreeState<L>,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.level
+            .may_load(storage)?
+            .is_none()
+            .then_some(())
+            .ok_or(MerkleTreeError::AlreadyInit)?;
+        Self::check_level(state.level)?;
 
-        let leaf = Blake2.hash_two(&Uint256::from_u128(5), &Uint256::from_u128(5))?;
+        let invalid = |reason: &str| MerkleTreeError::InvalidState(reason.to_string());
+        let default_leaf = state.zeros.first().ok_or_else(|| invalid("empty zeros"))?;
 
-        let (_, old_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
-        let (_, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+        (Self::compute_padding(state.level, default_leaf.clone(), state.padding, hasher)?
+            == state.zeros)
+            .then_some(())
+            .ok_or_else(|| invalid("zeros mismatch"))?;
+        algorithm::count_fits(state.leafs.len() as u64, state.level as usize)
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
 
-        assert!(!TREE.is_valid_root(&storage, &old_root)?);
-        assert!(TREE.is_valid_root(&storage, &new_root)?);
+        let path = state.level as usize;
+        let mut hashes = state.zeros.clone();
+        let mut root = None;
+
+        for (position, (index, leaf)) in state.leafs.iter().enumerate() {
+            (state.start_index.checked_add(position as u64) == Some(*index))
+                .then_some(())
+                .ok_or_else(|| invalid("leafs are not contiguous"))?;
+            Self::leaf_key(*index)?;
+            self.check_leaf_size(leaf, state.max_leaf_bytes)?;
+            root = Some(Self::append(
+                &mut hashes[..path],
+                &state.zeros[..path],
+                position as u64,
+                leaf.clone(),
+                hasher,
+            )?);
+        }
+
+        (hashes == state.frontier)
+            .then_some(())
+            .ok_or_else(|| invalid("frontier mismatch"))?;
+        (root == state.root)
+            .then_some(())
+            .ok_or_else(|| invalid("root mismatch"))?;
+
+        self.save_state(storage, &state)?;
+        self.save_config(storage, state.level, default_leaf.clone(), hasher.id())
+    }
+
+    /// Check if the stored frontier and root match the ones recomputed by replaying all stored leaves.
+    pub fn verify_integrity(
+        &self,
+        storage: &dyn Storage,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        let (zeros, frontier) = self.load_path(storage, self.level.load(storage)?)?;
+        let mut hashes = zeros.clone();
+        let mut root = None;
+
+        for (position, leaf) in self.load_leafs(storage)?.into_iter().enumerate() {
+            root = Some(Self::append(
+                &mut hashes,
+                &zeros,
+                position as u64,
+                leaf,
+                hasher,
+            )?);
+        }
+
+        Ok(hashes == frontier && root == self.root.may_load(storage)?)
+    }
+
+    /// Recompute the zeros, the frontier and the root from the stored leaves with the `new_hasher`, return the new root.
+    /// The stored state is checked against the `old_hasher` first, the leaves themselves are kept as is.
+    /// Proofs and roots computed before the migration are no longer valid against the tree.
+    pub fn rehash(
+        &self,
+        storage: &mut dyn Storage,
+        old_hasher: &H,
+        new_hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        self.verify_integrity(storage, old_hasher)?
+            .then_some(())
+            .ok_or_else(|| {
+                MerkleTreeError::StorageCorruption("tree mismatches the old hasher".to_string())
+            })?;
+
+        let level = self.level.load(storage)?;
+        let padding = self.padding.may_load(storage)?.unwrap_or_default();
+        let zeros = Self::compute_padding(level, self.default_leaf(storage)?, padding, new_hasher)?;
+        let path = level as usize;
+        let mut hashes = zeros.clone();
+        let mut root = None;
+
+        for (position, leaf) in self.load_leafs(storage)?.into_iter().enumerate() {
+            root = Some(Self::append(
+                &mut hashes[..path],
+                &zeros[..path],
+                position as u64,
+                leaf,
+                new_hasher,
+            )?);
+        }
+
+        Self::save_levels(storage, &self.hashes, &hashes)?;
+        Self::save_levels(storage, &self.zeros, &zeros)?;
+        if let Some(root) = &root {
+            self.root.save(storage, root)?;
+        }
+        self.save_config(storage, level, zeros[0].clone(), new_hasher.id())?;
+
+        self.get_latest_root(storage)
+    }
+
+    /// Reconstruct the root right after the first `n` leaves were inserted by replaying the stored leaves.
+    /// The root of the empty tree is the same as [MerkleTree::get_latest_root] before any insertion.
+    /// Return [MerkleTreeError::LeafNotFound] of the `n`th leaf if fewer than `n` leaves are inserted.
+    pub fn root_after_n_inserts(
+        &self,
+        storage: &dyn Storage,
+        n: u64,
+        hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        self.check_leafs_stored(storage)?;
+
+        let level = self.level.load(storage)?;
+        let (zeros, _) = self.load_path(storage, level)?;
+        let first_index = self.first_index(storage)?;
+        let mut hashes = zeros.clone();
+        let mut root = Self::load_level(storage, &self.zeros, "zeros", level.saturating_sub(1))?;
+        let mut position = 0;
+
+        for leaf in self
+            .leafs
+            .range(storage, None, None, Order::Ascending)
+            .take(n as usize)
+        {
+            root = Self::append(&mut hashes, &zeros, position, leaf?.1, hasher)?;
+            position += 1;
+        }
+
+        (position == n)
+            .then_some(root)
+            .ok_or_else(|| MerkleTreeError::LeafNotFound {
+                index: first_index + n - 1,
+            })
+    }
+
+    /// Deep copy the stored state of the tree into the uninitialized `dst` tree in the same storage.
+    /// The snapshot of a frozen tree is frozen as well.
+    pub fn snapshot_to(
+        &self,
+        storage: &mut dyn Storage,
+        dst: &Self,
+    ) -> Result<(), MerkleTreeError> {
+        dst.level
+            .may_load(storage)?
+            .is_none()
+            .then_some(())
+            .ok_or(MerkleTreeError::AlreadyInit)?;
+
+        let state = self.export_state(storage)?;
+        let config = self.config(storage)?;
+
+        dst.save_state(storage, &state)?;
+        dst.config.save(storage, &config)?;
+
+        Ok(())
+    }
+
+    /// Insert the `leaf` into the next index slot of the tree without saving the root.
+    /// The root is saved once by calling [SparseMerkleTree::commit_root] after the insertions.
+    pub fn insert_no_root(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<u64, MerkleTreeError> {
+        self.insert_leaf(storage, leaf, hasher)
+            .map(|(index, _, _)| index)
+    }
+
+    /// Compute the root from the frontier and save it as the latest root.
+    pub fn commit_root(&self, storage: &mut dyn Storage, hasher: &H) -> Result<L, MerkleTreeError> {
+        // Nothing is inserted since the init or the resume from a frontier, the latest root is already known
+        let Some(index) = self.latest_index(storage)? else {
+            return self.get_latest_root(storage);
+        };
+
+        let latest_root = self.compute_root(storage, index, hasher)?;
+        self.root.save(storage, &latest_root)?;
+
+        Ok(latest_root)
+    }
+
+    /// Write the `state` into the storage without any validation.
+    fn save_state(
+        &self,
+        storage: &mut dyn Storage,
+        state: &TreeState<L>,
+    ) -> Result<(), MerkleTreeError> {
+        self.level.save(storage, &state.level)?;
+        self.start_index.save(storage, &state.start_index)?;
+        if let Some(max_leaf_bytes) = state.max_leaf_bytes {
+            self.max_leaf_bytes.save(storage, &max_leaf_bytes)?;
+        }
+        if state.padding != Padding::RecursiveZero {
+            self.padding.save(storage, &state.padding)?;
+        }
+        if state.frozen {
+            self.frozen.save(storage, &true)?;
+        }
+        Self::save_levels(storage, &self.hashes, &state.frontier)?;
+        Self::save_levels(storage, &self.zeros, &state.zeros)?;
+        for (index, leaf) in state.leafs.iter() {
+            self.leafs.save(storage, Self::leaf_key(*index)?, leaf)?;
+        }
+        if let Some(root) = &state.root {
+            self.root.save(storage, root)?;
+        }
+
+        Ok(())
+    }
+
+    /// Freeze the tree so that no more leaves can be inserted, e.g. to finalize an airdrop.
+    /// The inserts return [MerkleTreeError::Frozen] afterward, the reads and the proofs are unaffected.
+    pub fn freeze(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.level(storage)?;
+
+        Ok(self.frozen.save(storage, &true)?)
+    }
+
+    /// Check if the tree is frozen by [SparseMerkleTree::freeze].
+    pub fn is_frozen(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        Ok(self.frozen.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// Increase the level of the full tree by one, the tree then becomes the left child of the new root.
+    /// Only the new top zero and frontier node are written, the stored leaves keep their index.
+    pub(crate) fn grow_level(
+        &self,
+        storage: &mut dyn Storage,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        let level = self.level.load(storage)?;
+        let config = self.config(storage)?;
+        let padding = self.padding.may_load(storage)?.unwrap_or_default();
+        let zeros = Self::compute_padding(level + 1, config.default_leaf.clone(), padding, hasher)?;
+        let root = self.get_latest_root(storage)?;
+
+        self.hashes.save(storage, level, &root)?;
+        self.zeros.save(storage, level, &zeros[level as usize])?;
+        self.level.save(storage, &(level + 1))?;
+        self.config.save(
+            storage,
+            &TreeConfig {
+                level: level + 1,
+                ..config
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Get the configuration of the tree in a single read.
+    /// The trees initialized before the configuration is stored have it derived from the level and the zeros, without the hasher.
+    pub fn config(&self, storage: &dyn Storage) -> Result<TreeConfig<L>, MerkleTreeError> {
+        if let Some(config) = self.config.may_load(storage)? {
+            return Ok(config);
+        }
+
+        Ok(TreeConfig {
+            level: self
+                .level
+                .may_load(storage)?
+                .ok_or(MerkleTreeError::NotInitialized)?,
+            default_leaf: self
+                .zeros
+                .may_load(storage, 0)?
+                .ok_or(MerkleTreeError::NotInitialized)?,
+            arity: 2,
+            hasher: None,
+        })
+    }
+
+    fn save_config(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: Option<String>,
+    ) -> Result<(), MerkleTreeError> {
+        Ok(self.config.save(
+            storage,
+            &TreeConfig {
+                level,
+                default_leaf,
+                arity: 2,
+                hasher,
+            },
+        )?)
+    }
+
+    fn first_index(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        Ok(self.start_index.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// Convert the `index` into the leaf key, fail if the key type can not represent it.
+    fn leaf_key(index: u64) -> Result<I, MerkleTreeError> {
+        I::from_index(index).ok_or(MerkleTreeError::ExceedMaxLeaf)
+    }
+
+    fn has_leaf(&self, storage: &dyn Storage, index: u64) -> bool {
+        I::from_index(index).is_some_and(|key| self.leafs.has(storage, key))
+    }
+
+    fn next_leaf_index(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        match self.latest_index(storage)? {
+            Some(index) => Ok(index + 1),
+            None => Ok(self.first_index(storage)?
+                + self.skipped_leaves.may_load(storage)?.unwrap_or_default()),
+        }
+    }
+
+    /// Return [MerkleTreeError::LeafNotFound] of the first leaf if the leaves are skipped by [SparseMerkleTree::init_from_frontier].
+    fn check_leafs_stored(&self, storage: &dyn Storage) -> Result<(), MerkleTreeError> {
+        match self.skipped_leaves.may_load(storage)? {
+            Some(_) => Err(MerkleTreeError::LeafNotFound {
+                index: self.first_index(storage)?,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Insert the `leaf` without saving the root, return its index, the updated root and its proof against the root.
+    fn insert_leaf(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        let insert = self.prepare_insert(storage, leaf, hasher)?;
+        self.apply_insert(storage, &insert)?;
+
+        Ok((insert.index, insert.root, insert.proof))
+    }
+
+    /// Check if the `leaf` can be inserted regardless of the capacity, the tree is not frozen and the leaf is valid.
+    pub(crate) fn check_insert(
+        &self,
+        storage: &dyn Storage,
+        leaf: &L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        (!self.is_frozen(storage)?)
+            .then_some(())
+            .ok_or(MerkleTreeError::Frozen)?;
+        hasher.validate_leaf(leaf)?;
+        self.check_leaf_size(leaf, self.max_leaf_bytes.may_load(storage)?)
+    }
+
+    /// Hash the path of the `leaf` inserted at the next index without writing the storage.
+    /// The siblings of the latest leaf are the zeros on its right and the frontier on its left.
+    pub(crate) fn prepare_insert(
+        &self,
+        storage: &dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<PreparedInsert<L>, MerkleTreeError> {
+        self.check_insert(storage, &leaf, hasher)?;
+
+        let index = self.next_index(storage)?;
+        let position = index - self.first_index(storage)?;
+
+        Self::leaf_key(index)?;
+        let level = self.level.load(storage)?;
+        self.check_levels(storage, level)?;
+        let mut path = Vec::with_capacity(level as usize);
+        let mut siblings = Vec::with_capacity(level as usize);
+        let mut cur_hash = leaf.clone();
+        let mut cur_idx = position;
+
+        // Same as [SparseMerkleTree::append] but only loads the one node needed at each level.
+        for i in 0..level {
+            let sibling = match cur_idx.is_multiple_of(2) {
+                true => Self::load_level(storage, &self.zeros, "zeros", i)?,
+                false => Self::load_level(storage, &self.hashes, "frontier", i)?,
+            };
+            let parent = match cur_idx.is_multiple_of(2) {
+                true => hasher.hash_two(&cur_hash, &sibling)?,
+                false => hasher.hash_two(&sibling, &cur_hash)?,
+            };
+            path.push(cur_hash);
+            siblings.push(sibling);
+            cur_hash = parent;
+            cur_idx /= 2;
+        }
+
+        Ok(PreparedInsert {
+            index,
+            leaf,
+            root: cur_hash,
+            proof: MerkleProof {
+                index: position,
+                siblings,
+            },
+            path,
+        })
+    }
+
+    /// Save the leaf and the frontier of the prepared `insert`, the root is saved by the caller.
+    pub(crate) fn apply_insert(
+        &self,
+        storage: &mut dyn Storage,
+        insert: &PreparedInsert<L>,
+    ) -> Result<(), MerkleTreeError> {
+        (self.on_insert)(storage, insert.index, &insert.leaf)?;
+
+        // Nothing is written until the whole path is hashed, a failing hasher leaves storage untouched.
+        self.leafs
+            .save(storage, Self::leaf_key(insert.index)?, &insert.leaf)?;
+        for (i, node) in insert.path.iter().enumerate() {
+            // The latest node of a level is its left node if the path goes left
+            let cur_idx = insert.proof.index.checked_shr(i as u32).unwrap_or_default();
+            if cur_idx.is_multiple_of(2) {
+                self.hashes.save(storage, i as u8, node)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the frontier `hashes` with the `leaf` at `position`, return the updated root.
+    pub(crate) fn append(
+        hashes: &mut [L],
+        zeros: &[L],
+        position: u64,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<L, HasherError> {
+        algorithm::append(hashes, zeros, position, leaf, |left, right| {
+            hasher.hash_two(left, right)
+        })
+    }
+
+    /// Compute the root by folding the path of the latest leaf at `index` like [SparseMerkleTree::insert_leaf].
+    /// The left siblings are in the frontier and the right siblings are the zeros, also when the tree is full.
+    fn compute_root(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        let level = self.level.load(storage)?;
+        self.check_levels(storage, level)?;
+        let mut cur_hash = self.leafs.load(storage, Self::leaf_key(index)?)?;
+        let mut cur_idx = index - self.first_index(storage)?;
+
+        for i in 0..level {
+            cur_hash = match cur_idx.is_multiple_of(2) {
+                true => hasher.hash_two_owned(
+                    cur_hash,
+                    Self::load_level(storage, &self.zeros, "zeros", i)?,
+                )?,
+                false => hasher.hash_two_owned(
+                    Self::load_level(storage, &self.hashes, "frontier", i)?,
+                    cur_hash,
+                )?,
+            };
+            cur_idx /= 2;
+        }
+
+        Ok(cur_hash)
+    }
+
+    /// Load the hash of the empty subtree of every level, ordered from the leaf level.
+    pub(crate) fn load_zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        Self::load_levels(storage, &self.zeros)
+    }
+
+    /// Load the latest left node of every level, ordered from the leaf level.
+    pub(crate) fn load_hashes(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        Self::load_levels(storage, &self.hashes)
+    }
+
+    /// Load the zeros and the frontier of the levels below the root, checked against the `level`.
+    /// The default leaf stored by a tree of level 0 is not a node of any path and is left out.
+    fn load_path(
+        &self,
+        storage: &dyn Storage,
+        level: u8,
+    ) -> Result<(Vec<L>, Vec<L>), MerkleTreeError> {
+        let mut zeros = self.load_zeros(storage)?;
+        let mut hashes = self.load_hashes(storage)?;
+        Self::check_len("zeros", zeros.len(), level)?;
+        Self::check_len("frontier", hashes.len(), level)?;
+        zeros.truncate(level as usize);
+        hashes.truncate(level as usize);
+
+        Ok((zeros, hashes))
+    }
+
+    fn load_levels(storage: &dyn Storage, map: &Map<'a, u8, L>) -> Result<Vec<L>, MerkleTreeError> {
+        Ok(map
+            .range(storage, None, None, Order::Ascending)
+            .map(|e| e.map(|(_, node)| node))
+            .collect::<StdResult<_>>()?)
+    }
+
+    fn save_levels(
+        storage: &mut dyn Storage,
+        map: &Map<'a, u8, L>,
+        nodes: &[L],
+    ) -> Result<(), MerkleTreeError> {
+        for (i, node) in nodes.iter().enumerate() {
+            map.save(storage, i as u8, node)?;
+        }
+
+        Ok(())
+    }
+
+    /// Return [MerkleTreeError::InvalidLevel] if the `level` is above [MAX_GROWABLE_LEVEL], the leaf positions are `u64`.
+    fn check_level(level: u8) -> Result<(), MerkleTreeError> {
+        (level <= MAX_GROWABLE_LEVEL)
+            .then_some(())
+            .ok_or(MerkleTreeError::InvalidLevel)
+    }
+
+    /// Get the [SparseMerkleTree::leaf_size] to measure the leaves against `max_leaf_bytes`.
+    fn leaf_size(&self) -> Result<fn(&L) -> usize, MerkleTreeError> {
+        self.leaf_size.ok_or_else(|| {
+            MerkleTreeError::InvalidState(
+                "max_leaf_bytes requires a tree built with_leaf_bytes".to_string(),
+            )
+        })
+    }
+
+    fn check_leaf_size(
+        &self,
+        leaf: &L,
+        max_leaf_bytes: Option<u64>,
+    ) -> Result<(), MerkleTreeError> {
+        let Some(max) = max_leaf_bytes else {
+            return Ok(());
+        };
+
+        let size = self.leaf_size()?(leaf) as u64;
+        (size <= max)
+            .then_some(())
+            .ok_or(MerkleTreeError::LeafTooLarge { size, max })
+    }
+
+    /// Check that the frontier and zeros end at `level` without loading them, like [SparseMerkleTree::check_len].
+    /// A missing node below the level is caught by [SparseMerkleTree::load_level] when it is loaded.
+    fn check_levels(&self, storage: &dyn Storage, level: u8) -> Result<(), MerkleTreeError> {
+        let len = level.max(1);
+
+        for (map, name) in [(&self.hashes, "frontier"), (&self.zeros, "zeros")] {
+            (map.has(storage, len - 1) && !map.has(storage, len))
+                .then_some(())
+                .ok_or_else(|| {
+                    MerkleTreeError::StorageCorruption(format!(
+                        "{name} length mismatches level {level}"
+                    ))
+                })?;
+        }
+
+        Ok(())
+    }
+
+    fn compute_padding(
+        level: u8,
+        default_leaf: L,
+        padding: Padding,
+        hasher: &H,
+    ) -> Result<Vec<L>, HasherError> {
+        match padding {
+            Padding::RecursiveZero => compute_zeros(level, default_leaf, hasher),
+            // Same length as the recursive zeros, a tree of level 0 still stores its default leaf
+            Padding::FixedSentinel => Ok(vec![default_leaf; (level as usize).max(1)]),
+        }
+    }
+
+    /// Check the stored length of the frontier or zeros, a tree of level 0 still stores its default leaf in both.
+    fn check_len(name: &str, len: usize, level: u8) -> Result<(), MerkleTreeError> {
+        (len == (level as usize).max(1))
+            .then_some(())
+            .ok_or_else(|| {
+                MerkleTreeError::StorageCorruption(format!(
+                    "{name} length {len} mismatches level {level}"
+                ))
+            })
+    }
+
+    fn load_level(
+        storage: &dyn Storage,
+        map: &Map<'a, u8, L>,
+        name: &str,
+        level: u8,
+    ) -> Result<L, MerkleTreeError> {
+        map.may_load(storage, level)?.ok_or_else(|| {
+            MerkleTreeError::StorageCorruption(format!("{name} is missing level {level}"))
+        })
+    }
+
+    fn load_leafs(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.check_leafs_stored(storage)?;
+
+        Ok(self
+            .leafs
+            .range(storage, None, None, Order::Ascending)
+            .map(|e| e.map(|(_, leaf)| leaf))
+            .collect::<StdResult<_>>()?)
+    }
+
+    /// Generate the proof of the leaf at `position` by folding the whole leaf `layer` up to the root.
+    pub(crate) fn fold_proof(
+        mut layer: Vec<L>,
+        zeros: &[L],
+        position: u64,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, HasherError> {
+        let mut cur_idx = position as usize;
+        let mut siblings = Vec::with_capacity(zeros.len());
+
+        for zero in zeros.iter() {
+            siblings.push(layer.get(cur_idx ^ 1).unwrap_or(zero).clone());
+
+            layer = Self::hash_layer(layer, zero, hasher)?;
+            cur_idx /= 2;
+        }
+
+        Ok(MerkleProof {
+            index: position,
+            siblings,
+        })
+    }
+
+    /// Generate the proofs of the leaves at `positions` like [SparseMerkleTree::fold_proof], folding the leaf `layer` once for all of them.
+    fn fold_proofs(
+        mut layer: Vec<L>,
+        zeros: &[L],
+        positions: &[u64],
+        hasher: &H,
+    ) -> Result<Vec<MerkleProof<L>>, HasherError> {
+        let mut proofs = positions
+            .iter()
+            .map(|&position| MerkleProof {
+                index: position,
+                siblings: Vec::with_capacity(zeros.len()),
+            })
+            .collect::<Vec<_>>();
+
+        for (i, zero) in zeros.iter().enumerate() {
+            for proof in proofs.iter_mut() {
+                let idx = proof.index.checked_shr(i as u32).unwrap_or_default() as usize;
+                proof
+                    .siblings
+                    .push(layer.get(idx ^ 1).unwrap_or(zero).clone());
+            }
+
+            layer = Self::hash_layer(layer, zero, hasher)?;
+        }
+
+        Ok(proofs)
+    }
+
+    fn hash_layer(layer: Vec<L>, zero: &L, hasher: &H) -> Result<Vec<L>, HasherError> {
+        let mut layer = layer.into_iter();
+        let mut next_layer = Vec::with_capacity(layer.len().div_ceil(2));
+
+        while let Some(left) = layer.next() {
+            let right = layer.next().unwrap_or_else(|| zero.clone());
+            next_layer.push(hasher.hash_two_owned(left, right)?);
+        }
+
+        Ok(next_layer)
+    }
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+        H: Hasher<L>,
+        I: LeafIndex,
+    > MerkleTree<L, H> for SparseMerkleTree<'a, L, H, I>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.init_with_start_index(storage, level, default_leaf, 0, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        Ok(self.root.may_load(storage)?.as_ref() == Some(root))
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        Ok(self.root.may_load(storage)?.is_some() as u64)
+    }
+
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        let (index, latest_root, _) = self.insert_leaf(storage, leaf, hasher)?;
+
+        self.root.save(storage, &latest_root)?;
+
+        Ok((index, latest_root))
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        let (index, latest_root, proof) = self.insert_leaf(storage, leaf, hasher)?;
+
+        self.root.save(storage, &latest_root)?;
+
+        Ok((index, latest_root, proof))
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        let level = self.level.load(storage)?;
+        self.check_levels(storage, level)?;
+
+        Ok(self
+            .root
+            .may_load(storage)?
+            .map_or_else(|| self.zeros.load(storage, level.saturating_sub(1)), Ok)?)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        Ok(I::from_index(index)
+            .map(|key| self.leafs.may_load(storage, key))
+            .transpose()?
+            .flatten())
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        Ok(self.config(storage)?.level)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        Ok(self.config(storage)?.default_leaf)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.level(storage)?;
+
+        self.load_zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        Ok(self.next_leaf_index(storage)? - self.first_index(storage)?)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        let level = self.level.load(storage)?;
+
+        Ok(!algorithm::position_fits(
+            self.leaf_count(storage)?,
+            level as usize,
+        ))
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        let proof = self.gen_proof(storage, index, hasher)?;
+        let leaf = self
+            .get_leaf(storage, index)?
+            .ok_or(MerkleTreeError::LeafNotFound { index })?;
+
+        Ok(proof.verify(&self.get_latest_root(storage)?, &leaf, hasher)?)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.leafs.clear(storage);
+        self.hashes.clear(storage);
+        self.zeros.clear(storage);
+        self.level.remove(storage);
+        self.root.remove(storage);
+        self.start_index.remove(storage);
+        self.max_leaf_bytes.remove(storage);
+        self.padding.remove(storage);
+        self.skipped_leaves.remove(storage);
+        self.config.remove(storage);
+        self.frozen.remove(storage);
+
+        Ok(())
+    }
+}
+
+/// Compute the hash of the empty subtree of each level, same as the zeros stored on init.
+pub fn compute_zeros<L, H: Hasher<L>>(
+    level: u8,
+    default_leaf: L,
+    hasher: &H,
+) -> Result<Vec<L>, HasherError> {
+    algorithm::compute_zeros(level, default_leaf, |left, right| {
+        hasher.hash_two(left, right)
+    })
+}
+
+/// Compute the root of the tree holding `next_index` leaves from its `frontier` and `zeros`, like [Frontier::append].
+/// Meant to validate a claimed root without the storage of the tree, e.g. off-chain.
+/// The frontier of a full tree does not determine its root and is rejected.
+pub fn root_from_frontier<L: Clone, H: Hasher<L>>(
+    frontier: &[L],
+    zeros: &[L],
+    next_index: u64,
+    hasher: &H,
+) -> Result<L, HasherError> {
+    algorithm::root_from_frontier(frontier, zeros, next_index, |left, right| {
+        hasher.hash_two(left, right)
+    })?
+    .ok_or_else(|| HasherError::custom("The frontier does not determine the root"))
+}
+
+/// Check if appending the `leaves` to the tree holding `next_index` leaves with the `frontier` and `zeros` results in the `root`.
+/// Meant to verify that a later snapshot only extends an earlier one, e.g. by a light client.
+/// Check the earlier root with [root_from_frontier] to also bind the frontier to it.
+pub fn verify_append<L: Clone + PartialEq, H: Hasher<L>>(
+    frontier: &[L],
+    zeros: &[L],
+    next_index: u64,
+    leaves: &[L],
+    root: &L,
+    hasher: &H,
+) -> Result<bool, HasherError> {
+    algorithm::verify_append(frontier, zeros, next_index, leaves, root, |left, right| {
+        hasher.hash_two(left, right)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, error::Error, str::FromStr};
+
+    use cosmwasm_std::{attr, from_slice, testing::MockStorage, to_vec, Order, StdResult, Uint256};
+    use cw_storage_plus::{Item, Map};
+
+    use crate::{
+        test_utils::{Blake2, NAMESPACES},
+        verify_multi_proof, Frontier, Hasher, HasherError, MerkleTree, MerkleTreeError, TreeConfig,
+    };
+
+    use super::{compute_zeros, root_from_frontier, verify_append, Padding, SparseMerkleTree};
+
+    const TREE: SparseMerkleTree<Uint256, Blake2> =
+        SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+    #[test]
+    fn init() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            Uint256::from_str(
+                "9249403463272353962338525770558810268347485650856754165003644360089862036530"
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn level_zero() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        TREE.init(&mut storage, 0, default_leaf, &Blake2)?;
+
+        // The only leaf is the root itself
+        assert_eq!(TREE.get_latest_root(&storage)?, default_leaf);
+        assert_eq!(TREE.preview_root(&storage, &[leaf], &Blake2)?, leaf);
+        assert_eq!(TREE.insert(&mut storage, leaf, &Blake2)?, (0, leaf));
+        assert_eq!(TREE.get_latest_root(&storage)?, leaf);
+        assert!(TREE.is_full(&storage)?);
+        assert!(matches!(
+            TREE.insert(&mut storage, leaf, &Blake2),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+
+        let proof = TREE.gen_proof(&storage, 0, &Blake2)?;
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&leaf, &leaf, &Blake2)?);
+        assert_eq!(TREE.gen_proof_at_root(&storage, 0, &leaf, &Blake2)?, proof);
+        assert_eq!(TREE.all_proofs(&storage, &Blake2)?, vec![(0, proof)]);
+        assert!(TREE.prove_and_verify(&storage, 0, &Blake2)?);
+        assert!(TREE.verify_integrity(&storage, &Blake2)?);
+        assert_eq!(
+            TREE.root_after_n_inserts(&storage, 0, &Blake2)?,
+            default_leaf
+        );
+        assert_eq!(TREE.root_after_n_inserts(&storage, 1, &Blake2)?, leaf);
+        assert_eq!(TREE.commit_root(&mut storage, &Blake2)?, leaf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_level() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        for level in [65, 128, 200] {
+            assert!(matches!(
+                TREE.init(&mut storage, level, default_leaf, &Blake2),
+                Err(MerkleTreeError::InvalidLevel)
+            ));
+        }
+        assert!(TREE.level.may_load(&storage)?.is_none());
+
+        // The positions of the tree of level 64 span the whole u64
+        TREE.init(&mut storage, 64, default_leaf, &Blake2)?;
+        let (_, root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert!(!TREE.is_full(&storage)?);
+        assert_eq!(TREE.remaining_capacity(&storage)?, u64::MAX);
+        assert!(TREE
+            .gen_proof(&storage, 0, &Blake2)?
+            .verify(&root, &leaf, &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn config() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        assert!(matches!(
+            TREE.config(&storage),
+            Err(MerkleTreeError::NotInitialized)
+        ));
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let config = TREE.config(&storage)?;
+
+        assert_eq!(
+            config,
+            TreeConfig {
+                level: 20,
+                default_leaf,
+                arity: 2,
+                hasher: Hasher::<Uint256>::id(&Blake2),
+            }
+        );
+        assert_eq!(TREE.config.load(&storage)?, config);
+        assert_eq!(
+            from_slice::<TreeConfig<Uint256>>(&to_vec(&config)?)?,
+            config
+        );
+
+        // The trees initialized before the config is stored derive it from the level and the zeros
+        TREE.config.remove(&mut storage);
+
+        assert_eq!(
+            TREE.config(&storage)?,
+            TreeConfig {
+                hasher: None,
+                ..config
+            }
+        );
+        assert_eq!(TREE.level(&storage)?, 20);
+        assert_eq!(TREE.default_leaf(&storage)?, default_leaf);
+
+        TREE.reset(&mut storage)?;
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+        TREE.reset(&mut storage)?;
+
+        assert!(TREE.config.may_load(&storage)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_storage() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+        let tree: SparseMerkleTree<Uint256, Blake2> =
+            SparseMerkleTree::try_from_storage(&storage, NAMESPACES, &Blake2)?;
+
+        assert_eq!(tree.get_latest_root(&storage)?, root);
+
+        // The root does not match the frontier
+        TREE.root.save(&mut storage, &Uint256::one())?;
+
+        assert!(matches!(
+            SparseMerkleTree::<Uint256, Blake2>::try_from_storage(&storage, NAMESPACES, &Blake2),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+
+        TREE.root.save(&mut storage, &root)?;
+        TREE.level.remove(&mut storage);
+
+        assert!(matches!(
+            SparseMerkleTree::<Uint256, Blake2>::try_from_storage(&storage, NAMESPACES, &Blake2),
+            Err(MerkleTreeError::NotInitialized)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_legacy_hashes() -> Result<(), Box<dyn Error>> {
+        const LEGACY_HASHES: Item<(Vec<Uint256>, Vec<Uint256>)> = Item::new("hashes");
+
+        let mut storage = MockStorage::new();
+        let mut expected_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let leafs = (1..=4)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TREE.init(&mut expected_storage, 20, default_leaf, &Blake2)?;
+        for leaf in &leafs {
+            TREE.insert(&mut expected_storage, *leaf, &Blake2)?;
+        }
+
+        // Layout of the earlier versions, the frontier and the zeros in a single item without a config
+        let zeros = compute_zeros(20, default_leaf, &Blake2)?;
+        let mut frontier = zeros.clone();
+        let mut root = zeros[19];
+        for (position, leaf) in leafs[..3].iter().enumerate() {
+            root = SparseMerkleTree::<Uint256, Blake2>::append(
+                &mut frontier,
+                &zeros,
+                position as u64,
+                *leaf,
+                &Blake2,
+            )?;
+            TREE.leafs.save(&mut storage, position as u64, leaf)?;
+        }
+        TREE.level.save(&mut storage, &20)?;
+        TREE.root.save(&mut storage, &root)?;
+        LEGACY_HASHES.save(&mut storage, &(frontier, zeros))?;
+
+        assert!(matches!(
+            TREE.insert(&mut storage, leafs[3], &Blake2),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+        assert!(TREE.migrate_legacy_hashes(&mut storage)?);
+        assert!(!TREE.migrate_legacy_hashes(&mut storage)?);
+        assert!(LEGACY_HASHES.may_load(&storage)?.is_none());
+
+        TREE.insert(&mut storage, leafs[3], &Blake2)?;
+
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            TREE.get_latest_root(&expected_storage)?
+        );
+        assert_eq!(TREE.default_leaf(&storage)?, default_leaf);
+        assert!(TREE.verify_integrity(&storage, &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn level() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        assert!(matches!(
+            TREE.level(&storage),
+            Err(MerkleTreeError::NotInitialized)
+        ));
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.level(&storage)?, 20);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        let (index, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(index, 0);
+        assert_eq!(
+            new_root,
+            Uint256::from_str(
+                "65270348628983318905821145914244198139930176154042934882987463098115489862117"
+            )?
+        );
+        assert_eq!(new_root, TREE.get_latest_root(&storage)?);
+        assert!(TREE.is_valid_root(&storage, &new_root)?);
+
+        let (index, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(index, 1);
+        assert_eq!(
+            new_root,
+            Uint256::from_str(
+                "31390868241958093005646829964058364480768696680064791450319134920411060649604"
+            )?
+        );
+        assert_eq!(new_root, TREE.get_latest_root(&storage)?);
+        assert!(TREE.is_valid_root(&storage, &new_root)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn root_history() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&Uint256::from_u128(5), &Uint256::from_u128(5))?;
+
+        let (_, old_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+        let (_, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert!(!TREE.is_valid_root(&storage, &old_root)?);
+        assert!(TREE.is_valid_root(&storage, &new_root)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn init_default() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut explicit_storage = MockStorage::new();
+
+        TREE.init_default(&mut storage, 20, &Blake2)?;
+        TREE.init(
+            &mut explicit_storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        assert_eq!(
+            TREE.default_leaf(&storage)?,
+            TREE.default_leaf(&explicit_storage)?
+        );
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            Uint256::from_str(
+                "9249403463272353962338525770558810268347485650856754165003644360089862036530"
+            )?
+        );
+        assert!(TREE.state_eq(&storage, &explicit_storage, &TREE)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn default_leaf() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        assert!(matches!(
+            TREE.default_leaf(&storage),
+            Err(MerkleTreeError::NotInitialized)
+        ));
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        assert_eq!(TREE.default_leaf(&storage)?, default_leaf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn zeros() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        assert!(matches!(
+            TREE.zeros(&storage),
+            Err(MerkleTreeError::NotInitialized)
+        ));
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+        TREE.insert(&mut storage, Uint256::one(), &Blake2)?;
+
+        assert_eq!(
+            TREE.zeros(&storage)?,
+            compute_zeros(20, default_leaf, &Blake2)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn next_index() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init_with_start_index(
+            &mut storage,
+            2,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            10,
+            &Blake2,
+        )?;
+
+        for i in 1..=4 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            let next_index = TREE.next_index(&storage)?;
+
+            assert_eq!(TREE.insert(&mut storage, leaf, &Blake2)?.0, next_index);
+        }
+
+        assert!(matches!(
+            TREE.next_index(&storage),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn remaining_capacity() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            2,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.remaining_capacity(&storage)?, 4);
+
+        for i in 1..=4 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+
+            assert_eq!(TREE.remaining_capacity(&storage)?, 4 - i as u64);
+        }
+
+        assert!(TREE.is_full(&storage)?);
+        assert_eq!(TREE.remaining_capacity(&storage)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn freeze() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        assert!(matches!(
+            TREE.freeze(&mut storage),
+            Err(MerkleTreeError::NotInitialized)
+        ));
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let leafs = (1..=3)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TREE.insert(&mut storage, leafs[0], &Blake2)?;
+        let (_, root) = TREE.insert(&mut storage, leafs[1], &Blake2)?;
+
+        assert!(!TREE.is_frozen(&storage)?);
+
+        TREE.freeze(&mut storage)?;
+
+        assert!(TREE.is_frozen(&storage)?);
+        assert!(matches!(
+            TREE.insert(&mut storage, leafs[2], &Blake2),
+            Err(MerkleTreeError::Frozen)
+        ));
+        assert_eq!(TREE.leaf_count(&storage)?, 2);
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+        assert_eq!(TREE.get_leaf(&storage, 1)?, Some(leafs[1]));
+        assert!(TREE
+            .gen_proof(&storage, 1, &Blake2)?
+            .verify(&root, &leafs[1], &Blake2)?);
+        assert!(TREE.prove_and_verify(&storage, 0, &Blake2)?);
+
+        TREE.reset(&mut storage)?;
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        assert_eq!(TREE.insert(&mut storage, leafs[2], &Blake2)?.0, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn init_idempotent() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init_idempotent(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let (_, root) = TREE.insert(&mut storage, Uint256::one(), &Blake2)?;
+
+        TREE.init_idempotent(&mut storage, 20, default_leaf, &Blake2)?;
+
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+        assert!(matches!(
+            TREE.init_idempotent(&mut storage, 19, default_leaf, &Blake2),
+            Err(MerkleTreeError::ConfigMismatch)
+        ));
+        assert!(matches!(
+            TREE.init_idempotent(&mut storage, 20, Uint256::zero(), &Blake2),
+            Err(MerkleTreeError::ConfigMismatch)
+        ));
+        assert!(matches!(
+            TREE.init(&mut storage, 20, default_leaf, &Blake2),
+            Err(MerkleTreeError::AlreadyInit)
+        ));
+
+        let config = TREE.config(&storage)?;
+        TREE.config.save(
+            &mut storage,
+            &TreeConfig {
+                hasher: Some("other".to_string()),
+                ..config.clone()
+            },
+        )?;
+
+        assert!(matches!(
+            TREE.init_idempotent(&mut storage, 20, default_leaf, &Blake2),
+            Err(MerkleTreeError::ConfigMismatch)
+        ));
+
+        TREE.config.save(
+            &mut storage,
+            &TreeConfig {
+                hasher: None,
+                ..config
+            },
+        )?;
+        TREE.init_idempotent(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let mut storage = MockStorage::new();
+        TREE.init_with_start_index(&mut storage, 20, default_leaf, 10, &Blake2)?;
+
+        assert!(matches!(
+            TREE.init_idempotent(&mut storage, 20, default_leaf, &Blake2),
+            Err(MerkleTreeError::ConfigMismatch)
+        ));
+
+        let mut storage = MockStorage::new();
+        TREE.with_leaf_bytes().init_with_max_leaf_bytes(
+            &mut storage,
+            20,
+            default_leaf,
+            32,
+            &Blake2,
+        )?;
+
+        assert!(matches!(
+            TREE.init_idempotent(&mut storage, 20, default_leaf, &Blake2),
+            Err(MerkleTreeError::ConfigMismatch)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn all_proofs() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init_with_start_index(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            10,
+            &Blake2,
+        )?;
+
+        assert!(TREE.all_proofs(&storage, &Blake2)?.is_empty());
+
+        let leafs = (1..=4)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+        let proofs = TREE.all_proofs(&storage, &Blake2)?;
+
+        assert_eq!(proofs.len(), 4);
+
+        for ((index, proof), leaf) in proofs.into_iter().zip(leafs.iter()) {
+            assert!(proof.verify(&root, leaf, &Blake2)?);
+            assert_eq!(proof, TREE.gen_proof(&storage, index, &Blake2)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn roots_count() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.roots_count(&storage)?, 0);
+
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+
+            assert_eq!(TREE.roots_count(&storage)?, 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_from_iter() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (indices, root) = TREE.insert_from_iter(&mut storage, leafs, &Blake2)?;
+
+        assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+        assert_eq!(
+            root,
+            Uint256::from_str(
+                "100259425028802178177846186514296156840305931743209248638432749269113773377665"
+            )?
+        );
+        assert_eq!(
+            TREE.insert_from_iter(&mut storage, [], &Blake2)?,
+            (vec![], root)
+        );
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 2, Uint256::zero(), &Blake2)?;
+        TREE.insert(&mut storage, Uint256::one(), &Blake2)?;
+
+        assert!(matches!(
+            TREE.insert_from_iter(&mut storage, vec![Uint256::one(); 4], &Blake2),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+        assert_eq!(TREE.leaf_count(&storage)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_leaves() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=3)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        TREE.insert_from_iter(&mut storage, leafs.clone(), &Blake2)?;
+
+        assert_eq!(
+            TREE.get_leaves(&storage, &[2, 5, 0, u64::MAX, 2])?,
+            vec![Some(leafs[2]), None, Some(leafs[0]), None, Some(leafs[2])]
+        );
+        assert_eq!(TREE.get_leaves(&storage, &[])?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_nonzero() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        assert!(matches!(
+            TREE.insert_nonzero(&mut storage, default_leaf, &Blake2),
+            Err(MerkleTreeError::DefaultLeaf)
+        ));
+        assert_eq!(TREE.leaf_count(&storage)?, 0);
+        assert_eq!(
+            TREE.insert_nonzero(&mut storage, Uint256::one(), &Blake2)?,
+            (0, TREE.get_latest_root(&storage)?)
+        );
+
+        // The unchecked insert still accepts the default leaf
+        assert_eq!(TREE.insert(&mut storage, default_leaf, &Blake2)?.0, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn try_insert() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            2,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 0..4 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            let (index, root) = TREE
+                .try_insert(&mut storage, leaf, &Blake2)?
+                .expect("tree is not full");
+
+            assert_eq!(index, i as u64);
+            assert_eq!(root, TREE.get_latest_root(&storage)?);
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+
+        assert_eq!(
+            TREE.try_insert(&mut storage, Uint256::one(), &Blake2)?,
+            None
+        );
+        assert_eq!(TREE.leaf_count(&storage)?, 4);
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+
+        Ok(())
+    }
+
+    #[test]
+    fn current_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.current_root(&storage)?, None);
+
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+        let (_, root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(TREE.current_root(&storage)?, Some(root));
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+
+        Ok(())
+    }
+
+    #[test]
+    fn latest_index() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init_with_start_index(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            10,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.latest_index(&storage)?, None);
+
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            let (index, _) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+            assert_eq!(TREE.latest_index(&storage)?, Some(index));
+        }
+
+        assert_eq!(TREE.latest_index(&storage)?, Some(12));
+
+        Ok(())
+    }
+
+    #[test]
+    fn storage_stats() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        let stats = TREE.storage_stats(&storage)?;
+        let node_bytes = to_vec(&TREE.get_latest_root(&storage)?)?.len() as u64;
+
+        assert_eq!(stats.leaves, 3);
+        assert_eq!(stats.history_roots, 0);
+        assert_eq!(stats.max_history_roots, Some(0));
+        assert_eq!(stats.estimated_bytes, 44 * node_bytes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_in_range() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let first_page = TREE.leaves_in_range(&storage, None, 3)?;
+        let second_page = TREE.leaves_in_range(&storage, Some(3), 3)?;
+
+        assert_eq!(
+            first_page,
+            vec![(0, leafs[0]), (1, leafs[1]), (2, leafs[2])]
+        );
+        assert_eq!(second_page, vec![(3, leafs[3]), (4, leafs[4])]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn gen_proof() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+
+        for (index, leaf) in leafs.iter().enumerate() {
+            let proof = TREE.gen_proof(&storage, index as u64, &Blake2)?;
+
+            assert_eq!(proof.index, index as u64);
+            assert_eq!(proof.siblings.len(), 20);
+            assert!(proof.verify(&root, leaf, &Blake2)?);
+            assert!(!proof.verify(&root, &leafs[(index + 1) % 5], &Blake2)?);
+        }
+
+        // The siblings of one leaf with the index of another
+        let mut mismatched_proof = TREE.gen_proof(&storage, 1, &Blake2)?;
+        mismatched_proof.index = 2;
+
+        assert!(!mismatched_proof.verify(&root, &leafs[1], &Blake2)?);
+        assert!(!mismatched_proof.verify(&root, &leafs[2], &Blake2)?);
+
+        mismatched_proof.index = 1 + (1 << 20);
+
+        assert!(!mismatched_proof.verify(&root, &leafs[1], &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_with_proofs() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let mut leafs = vec![];
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+            leafs.push(leaf);
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+        let mut start = None;
+        let mut pages = vec![];
+
+        loop {
+            let page = TREE.leaves_with_proofs(&storage, start, 2, &Blake2)?;
+            let Some((last_index, _, _)) = page.last() else {
+                break;
+            };
+            start = Some(last_index + 1);
+            pages.push(page);
+        }
+
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+
+        for (index, leaf, proof) in pages.into_iter().flatten() {
+            assert_eq!(leaf, leafs[index as usize]);
+            assert!(proof.verify(&root, &leaf, &Blake2)?);
+            assert_eq!(proof, TREE.gen_proof(&storage, index, &Blake2)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn gen_multi_proof() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+        let proof = TREE.gen_multi_proof(&storage, &[4, 0, 1], &Blake2)?;
+        let single_proofs_size = [0, 1, 4]
+            .into_iter()
+            .map(|index| Ok(TREE.gen_proof(&storage, index, &Blake2)?.siblings.len()))
+            .sum::<Result<usize, Box<dyn Error>>>()?;
+
+        assert_eq!(proof.indices, vec![0, 1, 4]);
+        assert!(proof.siblings.len() < single_proofs_size);
+        assert!(verify_multi_proof(
+            &Blake2,
+            &root,
+            &[leafs[0], leafs[1], leafs[4]],
+            &proof
+        )?);
+        assert!(!verify_multi_proof(
+            &Blake2,
+            &root,
+            &[leafs[0], leafs[2], leafs[4]],
+            &proof
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn zeros_split() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let zeros = TREE.load_zeros(&storage)?;
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        assert_eq!(TREE.load_zeros(&storage)?, zeros);
+        assert_eq!(zeros[0], default_leaf);
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            Uint256::from_str(
+                "100259425028802178177846186514296156840305931743209248638432749269113773377665"
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn compute_zeros_matches_init() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        assert_eq!(
+            compute_zeros(20, default_leaf, &Blake2)?,
+            TREE.load_zeros(&storage)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_no_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut lazy_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+        TREE.init(&mut lazy_storage, 20, default_leaf, &Blake2)?;
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            let (index, _) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+            assert_eq!(
+                TREE.insert_no_root(&mut lazy_storage, leaf, &Blake2)?,
+                index
+            );
+        }
+
+        assert!(TREE.root.may_load(&lazy_storage)?.is_none());
+
+        let root = TREE.commit_root(&mut lazy_storage, &Blake2)?;
+
+        assert_eq!(root, TREE.get_latest_root(&storage)?);
+        assert_eq!(root, TREE.get_latest_root(&lazy_storage)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn commit_root_full_tree() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut lazy_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 2, default_leaf, &Blake2)?;
+        TREE.init(&mut lazy_storage, 2, default_leaf, &Blake2)?;
+
+        let empty_root = TREE.commit_root(&mut lazy_storage, &Blake2)?;
+
+        for i in 1..=4 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+            TREE.insert_no_root(&mut lazy_storage, leaf, &Blake2)?;
+        }
+
+        // Every level of the last leaf's path goes right, none of it is in the frontier
+        let root = TREE.commit_root(&mut lazy_storage, &Blake2)?;
+
+        assert!(TREE.is_full(&lazy_storage)?);
+        assert_ne!(root, empty_root);
+        assert_eq!(root, TREE.get_latest_root(&storage)?);
+        assert!(TREE.verify_integrity(&lazy_storage, &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reset() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 10, default_leaf, &Blake2)?;
+
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+        let (_, old_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        TREE.reset(&mut storage)?;
+
+        assert!(TREE.leafs.is_empty(&storage));
+        assert!(!TREE.is_valid_root(&storage, &old_root)?);
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            Uint256::from_str(
+                "9249403463272353962338525770558810268347485650856754165003644360089862036530"
+            )?
+        );
+
+        let (index, _) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(index, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_sentinel_padding() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut new_storage = MockStorage::new();
+        let sentinel = Uint256::MAX;
+
+        TREE.init_with_padding(&mut storage, 2, sentinel, Padding::FixedSentinel, &Blake2)?;
+
+        assert_eq!(TREE.get_latest_root(&storage)?, sentinel);
+
+        let leafs = (1..=3)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Every empty right sibling is the sentinel, not the hash of the empty subtree
+        let (_, root) = TREE.insert(&mut storage, leafs[0], &Blake2)?;
+        assert_eq!(
+            root,
+            Blake2.hash_two(&Blake2.hash_two(&leafs[0], &sentinel)?, &sentinel)?
+        );
+
+        TREE.insert(&mut storage, leafs[1], &Blake2)?;
+        let (_, root) = TREE.insert(&mut storage, leafs[2], &Blake2)?;
+        assert_eq!(
+            root,
+            Blake2.hash_two(
+                &Blake2.hash_two(&leafs[0], &leafs[1])?,
+                &Blake2.hash_two(&leafs[2], &sentinel)?
+            )?
+        );
+
+        let proof = TREE.gen_proof(&storage, 2, &Blake2)?;
+
+        assert_eq!(proof.siblings[0], sentinel);
+        assert!(proof.verify(&root, &leafs[2], &Blake2)?);
+        assert!(matches!(
+            TREE.init_idempotent(&mut storage, 2, sentinel, &Blake2),
+            Err(MerkleTreeError::ConfigMismatch)
+        ));
+
+        let state = TREE.export_state(&storage)?;
+
+        assert_eq!(state.padding, Padding::FixedSentinel);
+
+        TREE.import_state(&mut new_storage, state, &Blake2)?;
+
+        assert_eq!(TREE.get_latest_root(&new_storage)?, root);
+        assert_eq!(TREE.padding.load(&new_storage)?, Padding::FixedSentinel);
+
+        Ok(())
+    }
+
+    #[test]
+    fn init_from_frontier() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut resumed_storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        let frontier = TREE.frontier(&storage)?;
+        let root = TREE.get_latest_root(&storage)?;
+
+        assert!(matches!(
+            TREE.init_from_frontier(
+                &mut resumed_storage,
+                20,
+                frontier.zeros.clone(),
+                frontier.frontier[1..].to_vec(),
+                frontier.next_index,
+                root,
+            ),
+            Err(MerkleTreeError::InvalidState(_))
+        ));
+
+        TREE.init_from_frontier(
+            &mut resumed_storage,
+            20,
+            frontier.zeros,
+            frontier.frontier,
+            frontier.next_index,
+            root,
+        )?;
+
+        assert_eq!(TREE.get_latest_root(&resumed_storage)?, root);
+        assert_eq!(TREE.leaf_count(&resumed_storage)?, 5);
+
+        // Appending continues the original tree
+        let leaf = Blake2.hash_two(&Uint256::from_u128(6), &Uint256::from_u128(6))?;
+
+        assert_eq!(
+            TREE.insert(&mut resumed_storage, leaf, &Blake2)?,
+            TREE.insert(&mut storage, leaf, &Blake2)?
+        );
+        assert_eq!(TREE.get_leaf(&resumed_storage, 5)?, Some(leaf));
+        assert_eq!(TREE.get_leaf(&resumed_storage, 4)?, None);
+
+        // The skipped leaves can not be replayed
+        assert!(matches!(
+            TREE.gen_proof(&resumed_storage, 5, &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 0 })
+        ));
+        assert!(matches!(
+            TREE.export_state(&resumed_storage),
+            Err(MerkleTreeError::LeafNotFound { index: 0 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_import_state() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut new_storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        let state = TREE.export_state(&storage)?;
+
+        let mut tampered_state = state.clone();
+        tampered_state.leafs[2].1 = Uint256::one();
+
+        assert!(matches!(
+            TREE.import_state(&mut new_storage, tampered_state, &Blake2),
+            Err(MerkleTreeError::InvalidState(_))
+        ));
+        assert!(TREE.level.may_load(&new_storage)?.is_none());
+
+        TREE.import_state(&mut new_storage, state, &Blake2)?;
+
+        assert_eq!(
+            TREE.get_latest_root(&new_storage)?,
+            TREE.get_latest_root(&storage)?
+        );
+        assert_eq!(
+            TREE.gen_proof(&new_storage, 3, &Blake2)?,
+            TREE.gen_proof(&storage, 3, &Blake2)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_to() -> Result<(), Box<dyn Error>> {
+        const SNAPSHOT_TREE: SparseMerkleTree<Uint256, Blake2> = SparseMerkleTree::new(
+            "snapshot_hashes",
+            "snapshot_leafs",
+            "snapshot_level",
+            "snapshot_root",
+        );
+
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        TREE.snapshot_to(&mut storage, &SNAPSHOT_TREE)?;
+
+        assert!(TREE.state_eq(&storage, &storage, &SNAPSHOT_TREE)?);
+        assert!(matches!(
+            TREE.snapshot_to(&mut storage, &SNAPSHOT_TREE),
+            Err(MerkleTreeError::AlreadyInit)
+        ));
+
+        let leaf = Blake2.hash_two(&Uint256::from_u128(4), &Uint256::from_u128(4))?;
+        let other_leaf = Blake2.hash_two(&Uint256::from_u128(5), &Uint256::from_u128(5))?;
+        let (index, root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+        let (snapshot_index, snapshot_root) =
+            SNAPSHOT_TREE.insert(&mut storage, other_leaf, &Blake2)?;
+
+        assert_eq!(index, snapshot_index);
+        assert_ne!(root, snapshot_root);
+        assert_eq!(TREE.get_leaf(&storage, 3)?, Some(leaf));
+        assert_eq!(SNAPSHOT_TREE.get_leaf(&storage, 3)?, Some(other_leaf));
+        assert!(!TREE.is_valid_root(&storage, &snapshot_root)?);
+
+        const FROZEN_TREE: SparseMerkleTree<Uint256, Blake2> = SparseMerkleTree::new(
+            "frozen_hashes",
+            "frozen_leafs",
+            "frozen_level",
+            "frozen_root",
+        );
+
+        TREE.freeze(&mut storage)?;
+        TREE.snapshot_to(&mut storage, &FROZEN_TREE)?;
+
+        assert!(FROZEN_TREE.is_frozen(&storage)?);
+        assert!(FROZEN_TREE.export_state(&storage)?.frozen);
+        assert!(matches!(
+            FROZEN_TREE.insert(&mut storage, other_leaf, &Blake2),
+            Err(MerkleTreeError::Frozen)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_integrity() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        assert!(TREE.verify_integrity(&storage, &Blake2)?);
+
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        assert!(TREE.verify_integrity(&storage, &Blake2)?);
+
+        TREE.hashes.save(&mut storage, 1, &Uint256::one())?;
+
+        assert!(!TREE.verify_integrity(&storage, &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn frontier() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=4 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        let mut frontier = TREE.frontier(&storage)?;
+
+        assert_eq!(frontier.next_index, 4);
+
+        let leaf = Blake2.hash_two(&Uint256::from_u128(5), &Uint256::from_u128(5))?;
+        let root = frontier.append(leaf, &Blake2)?;
+        let (_, latest_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(root, latest_root);
+        assert_eq!(frontier, TREE.frontier(&storage)?);
+
+        // The frontier of more than 64 levels is rejected instead of overflowing
+        let mut deep_frontier = Frontier {
+            frontier: vec![leaf; 200],
+            zeros: vec![leaf; 200],
+            next_index: 1,
+        };
+        assert!(matches!(
+            deep_frontier.append(leaf, &Blake2),
+            Err(MerkleTreeError::InvalidLevel)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn preview_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            3,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=9)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(
+            TREE.preview_root(&storage, &[], &Blake2)?,
+            TREE.get_latest_root(&storage)?
+        );
+
+        TREE.insert_from_iter(&mut storage, leafs[..2].to_vec(), &Blake2)?;
+
+        let preview = TREE.preview_root(&storage, &leafs[2..5], &Blake2)?;
+
+        // Nothing is written
+        assert_eq!(TREE.leaf_count(&storage)?, 2);
+        assert_ne!(TREE.get_latest_root(&storage)?, preview);
+
+        TREE.insert_from_iter(&mut storage, leafs[2..5].to_vec(), &Blake2)?;
+
+        assert_eq!(TREE.get_latest_root(&storage)?, preview);
+
+        assert!(matches!(
+            TREE.preview_root(&storage, &leafs[5..], &Blake2),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+        assert_eq!(TREE.leaf_count(&storage)?, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn root_from_frontier_matches_latest_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 0..=8 {
+            if i > 0 {
+                let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+                TREE.insert(&mut storage, leaf, &Blake2)?;
+            }
+
+            let frontier = TREE.frontier(&storage)?;
+
+            assert_eq!(
+                root_from_frontier(
+                    &frontier.frontier,
+                    &frontier.zeros,
+                    frontier.next_index,
+                    &Blake2
+                )?,
+                TREE.get_latest_root(&storage)?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_append_extends_snapshot() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TREE.insert_from_iter(&mut storage, leafs[..2].to_vec(), &Blake2)?;
+
+        let old = TREE.frontier(&storage)?;
+        let (_, new_root) = TREE.insert_from_iter(&mut storage, leafs[2..].to_vec(), &Blake2)?;
+
+        assert!(verify_append(
+            &old.frontier,
+            &old.zeros,
+            old.next_index,
+            &leafs[2..],
+            &new_root,
+            &Blake2
+        )?);
+        assert!(!verify_append(
+            &old.frontier,
+            &old.zeros,
+            old.next_index,
+            &[leafs[2], leafs[4], leafs[3]],
+            &new_root,
+            &Blake2
+        )?);
+        assert!(!verify_append(
+            &old.frontier,
+            &old.zeros,
+            old.next_index + 1,
+            &leafs[2..],
+            &new_root,
+            &Blake2
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn level_keyed_frontier() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let zeros = compute_zeros(20, default_leaf, &Blake2)?;
+        let mut hashes = zeros.clone();
+
+        assert_eq!(TREE.load_zeros(&storage)?, zeros);
+
+        for i in 0..8 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            let root = SparseMerkleTree::<Uint256, Blake2>::append(
+                &mut hashes,
+                &zeros,
+                i as u64,
+                leaf,
+                &Blake2,
+            )?;
+
+            assert_eq!(TREE.insert(&mut storage, leaf, &Blake2)?.1, root);
+            assert_eq!(TREE.load_hashes(&storage)?, hashes);
+        }
+
+        assert_eq!(
+            TREE.commit_root(&mut storage, &Blake2)?,
+            TREE.get_latest_root(&storage)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn storage_corruption() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+        TREE.insert(&mut storage, Uint256::one(), &Blake2)?;
+        TREE.hashes.save(&mut storage, 20, &Uint256::one())?;
+
+        assert!(matches!(
+            TREE.get_latest_root(&storage),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+        assert!(matches!(
+            TREE.insert(&mut storage, Uint256::one(), &Blake2),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+        assert!(matches!(
+            TREE.gen_proof(&storage, 0, &Blake2),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+
+        // The leaf at position 1 is hashed with the frontier node of level 0
+        TREE.hashes.remove(&mut storage, 20);
+        TREE.hashes.remove(&mut storage, 0);
+
+        assert!(matches!(
+            TREE.insert(&mut storage, Uint256::one(), &Blake2),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+        assert!(matches!(
+            TREE.gen_proof(&storage, 0, &Blake2),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+        assert_eq!(TREE.leaf_count(&storage)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn zeros_length_mismatch() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        // Partially cleared zeros of a shorter tree under the level of a taller one
+        TREE.zeros.remove(&mut storage, 19);
+
+        assert!(matches!(
+            TREE.get_latest_root(&storage),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+        assert!(matches!(
+            TREE.insert(&mut storage, Uint256::one(), &Blake2),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+        assert_eq!(TREE.leaf_count(&storage)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_leaf() -> Result<(), Box<dyn Error>> {
+        #[derive(Clone, Debug)]
+        struct Commitment;
+
+        impl Hasher<Vec<u8>> for Commitment {
+            fn hash_two(&self, left: &Vec<u8>, right: &Vec<u8>) -> Result<Vec<u8>, HasherError> {
+                Blake2.hash_two(left, right)
+            }
+
+            fn validate_leaf(&self, leaf: &Vec<u8>) -> Result<(), MerkleTreeError> {
+                (leaf.len() == 32)
+                    .then_some(())
+                    .ok_or_else(|| MerkleTreeError::InvalidLeaf("not 32 bytes".to_string()))
+            }
+        }
+
+        const COMMITMENT_TREE: SparseMerkleTree<Vec<u8>, Commitment> =
+            SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+        let mut storage = MockStorage::new();
+
+        COMMITMENT_TREE.init(&mut storage, 20, vec![0; 32], &Commitment)?;
+
+        assert!(matches!(
+            COMMITMENT_TREE.insert(&mut storage, vec![1; 31], &Commitment),
+            Err(MerkleTreeError::InvalidLeaf(_))
+        ));
+        assert!(matches!(
+            COMMITMENT_TREE.insert_no_root(&mut storage, vec![], &Commitment),
+            Err(MerkleTreeError::InvalidLeaf(_))
+        ));
+        assert_eq!(COMMITMENT_TREE.leaf_count(&storage)?, 0);
+        assert_eq!(
+            COMMITMENT_TREE
+                .insert(&mut storage, vec![1; 32], &Commitment)?
+                .0,
+            0
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_atomic_on_hasher_failure() -> Result<(), Box<dyn Error>> {
+        /// Fail once `remaining` hashes have been computed.
+        #[derive(Clone, Debug)]
+        struct Failing {
+            remaining: Cell<u32>,
+        }
+
+        impl Hasher<Uint256> for Failing {
+            fn hash_two(&self, left: &Uint256, right: &Uint256) -> Result<Uint256, HasherError> {
+                let remaining = self.remaining.get();
+                if remaining == 0 {
+                    return Err(HasherError::custom("hasher failed"));
+                }
+                self.remaining.set(remaining - 1);
+                Blake2.hash_two(left, right)
+            }
+        }
+
+        const FAILING_TREE: SparseMerkleTree<Uint256, Failing> =
+            SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+        let mut storage = MockStorage::new();
+        let hasher = Failing {
+            remaining: Cell::new(u32::MAX),
+        };
+
+        FAILING_TREE.init(&mut storage, 20, Uint256::zero(), &hasher)?;
+        FAILING_TREE.insert(&mut storage, Uint256::one(), &hasher)?;
+
+        let state = FAILING_TREE.export_state(&storage)?;
+
+        // Fail while hashing the 6th level of the path.
+        hasher.remaining.set(5);
+
+        assert!(matches!(
+            FAILING_TREE.insert(&mut storage, Uint256::from_u128(2), &hasher),
+            Err(MerkleTreeError::Hasher(_))
+        ));
+        assert_eq!(FAILING_TREE.leaf_count(&storage)?, 1);
+        assert_eq!(FAILING_TREE.get_leaf(&storage, 1)?, None);
+        assert_eq!(FAILING_TREE.export_state(&storage)?, state);
+
+        hasher.remaining.set(u32::MAX);
+
+        assert_eq!(
+            FAILING_TREE
+                .insert(&mut storage, Uint256::from_u128(2), &hasher)?
+                .0,
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn on_insert() -> Result<(), Box<dyn Error>> {
+        const INSERTED: Map<u64, Uint256> = Map::new("inserted");
+        const CALLS: Item<u64> = Item::new("calls");
+
+        const RECORDER_TREE: SparseMerkleTree<Uint256, Blake2> = SparseMerkleTree::new(
+            "hashes", "leafs", "level", "root",
+        )
+        .with_on_insert(|storage, index, leaf| {
+            INSERTED.save(storage, index, leaf)?;
+            CALLS.update(storage, |calls| Ok::<_, MerkleTreeError>(calls + 1))?;
+
+            Ok(())
+        });
+
+        let mut storage = MockStorage::new();
+
+        RECORDER_TREE.init_with_start_index(&mut storage, 20, Uint256::zero(), 10, &Blake2)?;
+        CALLS.save(&mut storage, &0)?;
+
+        for i in 1..=3 {
+            RECORDER_TREE.insert(&mut storage, Uint256::from_u128(i), &Blake2)?;
+        }
+        RECORDER_TREE.insert_no_root(&mut storage, Uint256::from_u128(4), &Blake2)?;
+
+        assert_eq!(CALLS.load(&storage)?, 4);
+        assert_eq!(
+            INSERTED
+                .range(&storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?,
+            (1..=4)
+                .map(|i| (i + 9, Uint256::from_u128(i as u128)))
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_leaf_bytes() -> Result<(), Box<dyn Error>> {
+        const BYTES_TREE: SparseMerkleTree<Vec<u8>, Blake2> =
+            SparseMerkleTree::new("hashes", "leafs", "level", "root").with_leaf_bytes();
+
+        let mut storage = MockStorage::new();
+
+        assert!(matches!(
+            TREE.init_with_max_leaf_bytes(&mut storage, 20, Uint256::zero(), 32, &Blake2),
+            Err(MerkleTreeError::InvalidState(_))
+        ));
+        assert!(matches!(
+            TREE.with_leaf_bytes().init_with_max_leaf_bytes(
+                &mut storage,
+                20,
+                Uint256::zero(),
+                31,
+                &Blake2
+            ),
+            Ok(())
+        ));
+        assert!(matches!(
+            TREE.with_leaf_bytes()
+                .insert(&mut storage, Uint256::one(), &Blake2),
+            Err(MerkleTreeError::LeafTooLarge { size: 32, max: 31 })
+        ));
+
+        let mut storage = MockStorage::new();
+
+        BYTES_TREE.init_with_max_leaf_bytes(&mut storage, 20, vec![0; 32], 32, &Blake2)?;
+
+        assert!(matches!(
+            BYTES_TREE.insert(&mut storage, vec![1; 33], &Blake2),
+            Err(MerkleTreeError::LeafTooLarge { size: 33, max: 32 })
+        ));
+        assert_eq!(BYTES_TREE.leaf_count(&storage)?, 0);
+        assert_eq!(BYTES_TREE.insert(&mut storage, vec![1; 32], &Blake2)?.0, 0);
+        assert_eq!(BYTES_TREE.export_state(&storage)?.max_leaf_bytes, Some(32));
+
+        Ok(())
+    }
+
+    #[test]
+    fn init_with_seed() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut seed_storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+        TREE.init_with_seed(&mut seed_storage, 20, Uint256::zero(), &Blake2)?;
+
+        assert!(TREE.state_eq(&seed_storage, &storage, &TREE)?);
+        assert_eq!(TREE.load_zeros(&seed_storage)?, TREE.load_zeros(&storage)?);
+        assert_eq!(
+            TREE.get_latest_root(&seed_storage)?,
+            Uint256::from_str(
+                "9249403463272353962338525770558810268347485650856754165003644360089862036530"
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_raw() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut raw_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+        TREE.init(&mut raw_storage, 20, default_leaf, &Blake2)?;
+
+        let preimage = Uint256::one();
+
+        assert_eq!(
+            TREE.insert_raw(&mut raw_storage, preimage, &Blake2)?,
+            TREE.insert(&mut storage, Blake2.hash_leaf(&preimage)?, &Blake2)?
+        );
+        assert_eq!(
+            TREE.get_latest_root(&raw_storage)?,
+            Uint256::from_str(
+                "65270348628983318905821145914244198139930176154042934882987463098115489862117"
+            )?
+        );
+
+        let leaf = Blake2.hash_leaf(&preimage)?;
+
+        assert_eq!(TREE.leafs.load(&raw_storage, 0)?, leaf);
+        assert_eq!(TREE.get_leaf(&raw_storage, 0)?, Some(leaf));
+        assert!(TREE.gen_proof(&raw_storage, 0, &Blake2)?.verify(
+            &TREE.get_latest_root(&raw_storage)?,
+            &leaf,
+            &Blake2
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn init_with_leaves() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut batch_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let leafs = (1..=4)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let root =
+            TREE.init_with_leaves(&mut batch_storage, 20, default_leaf, leafs.clone(), &Blake2)?;
+
+        assert_eq!(root, TREE.get_latest_root(&storage)?);
+        assert_eq!(root, TREE.get_latest_root(&batch_storage)?);
+        assert!(matches!(
+            TREE.init_with_leaves(
+                &mut MockStorage::new(),
+                1,
+                default_leaf,
+                leafs.clone(),
+                &Blake2
+            ),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+
+        // The leaves fill the tree exactly
+        let mut full_storage = MockStorage::new();
+        let mut full_batch_storage = MockStorage::new();
+
+        TREE.init(&mut full_storage, 2, default_leaf, &Blake2)?;
+        for leaf in leafs.iter() {
+            TREE.insert(&mut full_storage, *leaf, &Blake2)?;
+        }
+
+        let root =
+            TREE.init_with_leaves(&mut full_batch_storage, 2, default_leaf, leafs, &Blake2)?;
+
+        assert!(TREE.is_full(&full_batch_storage)?);
+        assert_eq!(root, TREE.get_latest_root(&full_storage)?);
+        assert_eq!(root, TREE.get_latest_root(&full_batch_storage)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_returning_proof() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init_with_start_index(
+            &mut storage,
+            3,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            10,
+            &Blake2,
+        )?;
+
+        for i in 1..=8 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            let (index, root, proof) = TREE.insert_returning_proof(&mut storage, leaf, &Blake2)?;
+
+            assert_eq!(root, TREE.get_latest_root(&storage)?);
+            assert!(proof.verify(&root, &leaf, &Blake2)?);
+            assert_eq!(proof, TREE.gen_proof(&storage, index, &Blake2)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_with_event() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        let (index, new_root, attributes) = TREE.insert_with_event(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(index, 1);
+        assert_eq!(new_root, TREE.get_latest_root(&storage)?);
+        assert_eq!(
+            attributes,
+            vec![
+                attr("leaf_index", "1"),
+                attr(
+                    "root",
+                    "45669a0f95bb9d1a7bf83243b1cf068f5e50f2117f1a5ec5dedcfff588143e84"
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn state_eq() -> Result<(), Box<dyn Error>> {
+        const OTHER_TREE: SparseMerkleTree<Uint256, Blake2> =
+            SparseMerkleTree::new("other_hashes", "other_leafs", "other_level", "other_root");
+
+        let mut storage = MockStorage::new();
+        let mut other_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+        OTHER_TREE.init(&mut other_storage, 20, default_leaf, &Blake2)?;
+
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+            OTHER_TREE.insert(&mut other_storage, leaf, &Blake2)?;
+        }
+
+        assert!(TREE.state_eq(&storage, &other_storage, &OTHER_TREE)?);
+
+        OTHER_TREE.insert(&mut other_storage, default_leaf, &Blake2)?;
+
+        assert!(!TREE.state_eq(&storage, &other_storage, &OTHER_TREE)?);
+        assert!(!OTHER_TREE.state_eq(&other_storage, &storage, &TREE)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn start_index() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut offset_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+        let leafs = (1..=2)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TREE.init(&mut storage, 1, default_leaf, &Blake2)?;
+        TREE.init_with_start_index(&mut offset_storage, 1, default_leaf, 100, &Blake2)?;
+
+        assert_eq!(TREE.leaf_count(&offset_storage)?, 0);
+
+        for (i, leaf) in leafs.iter().enumerate() {
+            let (index, root) = TREE.insert(&mut offset_storage, *leaf, &Blake2)?;
+            let (_, expected_root) = TREE.insert(&mut storage, *leaf, &Blake2)?;
+
+            assert_eq!(index, 100 + i as u64);
+            assert_eq!(root, expected_root);
+        }
+
+        assert_eq!(TREE.leaf_count(&offset_storage)?, 2);
+        assert_eq!(TREE.get_leaf(&offset_storage, 101)?, Some(leafs[1]));
+        assert!(TREE.gen_proof(&offset_storage, 101, &Blake2)?.verify(
+            &TREE.get_latest_root(&offset_storage)?,
+            &leafs[1],
+            &Blake2
+        )?);
+        assert!(matches!(
+            TREE.insert(&mut offset_storage, default_leaf, &Blake2),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_checked() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            1,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        let first = TREE.insert_checked(&mut storage, leaf, &Blake2)?;
+        let second = TREE.insert_checked(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(first.index, 0);
+        assert!(!first.is_full);
+        assert_eq!(second.index, 1);
+        assert_eq!(second.root, TREE.get_latest_root(&storage)?);
+        assert!(second.is_full);
+        assert!(TREE.is_full(&storage)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_size_leaf() -> Result<(), Box<dyn Error>> {
+        const FIXED_TREE: SparseMerkleTree<[u8; 32], Blake2> =
+            SparseMerkleTree::new("hashes", "leafs", "level", "root");
+        const VEC_TREE: SparseMerkleTree<Vec<u8>, Blake2> =
+            SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+        let mut fixed_storage = MockStorage::new();
+        let mut vec_storage = MockStorage::new();
+        let zero = [0u8; 32];
+
+        FIXED_TREE.init(
+            &mut fixed_storage,
+            20,
+            Blake2.hash_two(&zero, &zero)?,
+            &Blake2,
+        )?;
+        VEC_TREE.init(
+            &mut vec_storage,
+            20,
+            Blake2.hash_two(&zero.to_vec(), &zero.to_vec())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=3 {
+            let bytes = Uint256::from_u128(i).to_be_bytes();
+            let (_, fixed_root) = FIXED_TREE.insert(
+                &mut fixed_storage,
+                Blake2.hash_two(&bytes, &bytes)?,
+                &Blake2,
+            )?;
+            let (_, vec_root) = VEC_TREE.insert(
+                &mut vec_storage,
+                Blake2.hash_two(&bytes.to_vec(), &bytes.to_vec())?,
+                &Blake2,
+            )?;
+
+            assert_eq!(fixed_root.to_vec(), vec_root);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn prove_and_verify() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=3 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            TREE.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        assert!(TREE.prove_and_verify(&storage, 2, &Blake2)?);
+        assert!(matches!(
+            TREE.prove_and_verify(&storage, 3, &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 3 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn gen_proof_leaf_not_found() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&Uint256::one(), &Uint256::one())?;
+
+        TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert!(matches!(
+            TREE.gen_proof(&storage, 1, &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 1 })
+        ));
+        assert!(matches!(
+            TREE.gen_multi_proof(&storage, &[0, 5], &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 5 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn leaf_index_type() -> Result<(), Box<dyn Error>> {
+        const U32_TREE: SparseMerkleTree<Uint256, Blake2, u32> =
+            SparseMerkleTree::new("hashes", "leafs", "level", "root");
+
+        let mut storage = MockStorage::new();
+        let mut u32_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+        U32_TREE.init(&mut u32_storage, 20, default_leaf, &Blake2)?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (index, leaf) in leafs.iter().enumerate() {
+            assert_eq!(
+                U32_TREE.insert(&mut u32_storage, *leaf, &Blake2)?,
+                TREE.insert(&mut storage, *leaf, &Blake2)?
+            );
+            assert_eq!(U32_TREE.get_leaf(&u32_storage, index as u64)?, Some(*leaf));
+        }
+
+        let root = U32_TREE.get_latest_root(&u32_storage)?;
+
+        assert_eq!(U32_TREE.leaf_count(&u32_storage)?, 5);
+        assert_eq!(U32_TREE.get_leaf(&u32_storage, u64::MAX)?, None);
+        assert_eq!(
+            U32_TREE.leaves_in_range(&u32_storage, Some(3), 10)?,
+            vec![(3, leafs[3]), (4, leafs[4])]
+        );
+
+        for (index, leaf) in leafs.iter().enumerate() {
+            let proof = U32_TREE.gen_proof(&u32_storage, index as u64, &Blake2)?;
+
+            assert!(proof.verify(&root, leaf, &Blake2)?);
+        }
+
+        // The index past the key type is rejected instead of truncated
+        const U8_TREE: SparseMerkleTree<Uint256, Blake2, u8> =
+            SparseMerkleTree::new("u8_hashes", "u8_leafs", "u8_level", "u8_root");
+
+        U8_TREE.init_with_start_index(&mut u32_storage, 2, default_leaf, 255, &Blake2)?;
+
+        assert_eq!(U8_TREE.insert(&mut u32_storage, leafs[0], &Blake2)?.0, 255);
+        assert!(matches!(
+            U8_TREE.insert(&mut u32_storage, leafs[1], &Blake2),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+        assert_eq!(U8_TREE.leaf_count(&u32_storage)?, 1);
+        assert_eq!(U8_TREE.get_leaf(&u32_storage, 255)?, Some(leafs[0]));
+        assert_eq!(U8_TREE.get_leaf(&u32_storage, 256)?, None);
 
         Ok(())
     }
@@ -0,0 +1,659 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::{Order, StdResult, Storage};
+use cw_storage_plus::Map;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{algorithm, Hasher, MerkleProof, MerkleTree, MerkleTreeError};
+
+use super::{SparseMerkleTree, TreeNamespaces};
+
+/// Like [SparseMerkleTree] but also stores every non-empty internal node below the root.
+pub struct SparseMerkleTreeFullNode<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+    H: Hasher<L>,
+> {
+    pub tree: SparseMerkleTree<'a, L, H>,
+    /// Node hash keyed by its level and its position in the level, level 0 is the leaf level.
+    pub nodes: Map<'a, (u8, u64), L>,
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>>
+    SparseMerkleTreeFullNode<'a, L, H>
+{
+    pub const fn new(
+        hashes_ns: &'a str,
+        leafs_ns: &'a str,
+        level_ns: &'a str,
+        root_ns: &'a str,
+        nodes_ns: &'a str,
+    ) -> Self {
+        Self {
+            tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
+            nodes: Map::new(nodes_ns),
+        }
+    }
+
+    /// Like [SparseMerkleTreeFullNode::new] but with the tree namespaces set by name.
+    pub const fn from_namespaces(tree: TreeNamespaces<'a>, nodes_ns: &'a str) -> Self {
+        Self {
+            tree: SparseMerkleTree::from_namespaces(tree),
+            nodes: Map::new(nodes_ns),
+        }
+    }
+
+    /// Get the root of the subtree at `level` and `index`, the index is counted from the first leaf.
+    /// Return the zero of the level if the subtree is empty, the node at the tree level is the root.
+    pub fn node_at(
+        &self,
+        storage: &dyn Storage,
+        level: u8,
+        index: u64,
+    ) -> Result<L, MerkleTreeError> {
+        let tree_level = self.tree.level.load(storage)?;
+
+        (level <= tree_level && algorithm::position_fits(index, (tree_level - level) as usize))
+            .then_some(())
+            .ok_or(MerkleTreeError::NodeOutOfRange { level, index })?;
+
+        if level == tree_level {
+            return self.tree.get_latest_root(storage);
+        }
+
+        match self.nodes.may_load(storage, (level, index))? {
+            Some(node) => Ok(node),
+            None => Ok(self.tree.zeros.load(storage, level)?),
+        }
+    }
+
+    /// Generate the proof of the leaf at `index` against the latest root like [SparseMerkleTree::gen_proof].
+    /// The siblings are read from the stored nodes instead of folding all inserted leaves, the zero of the level if the sibling is empty.
+    /// Return [MerkleTreeError::LeafNotFound] if the leaf at `index` is not inserted.
+    pub fn gen_proof(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
+        if self.tree.get_leaf(storage, index)?.is_none() {
+            return Err(MerkleTreeError::LeafNotFound { index });
+        }
+
+        let level = self.tree.level.load(storage)?;
+        let start_index = self.tree.start_index.may_load(storage)?.unwrap_or_default();
+        let position = index - start_index;
+        let siblings = (0..level)
+            .map(|i| {
+                let sibling = position.checked_shr(i as u32).unwrap_or_default() ^ 1;
+                match self.nodes.may_load(storage, (i, sibling))? {
+                    Some(node) => Ok(node),
+                    None => Ok(self.tree.zeros.load(storage, i)?),
+                }
+            })
+            .collect::<Result<_, MerkleTreeError>>()?;
+
+        Ok(MerkleProof {
+            index: position,
+            siblings,
+        })
+    }
+
+    /// Deep copy the tree like [SparseMerkleTree::snapshot_to] together with the stored nodes.
+    pub fn snapshot_to(
+        &self,
+        storage: &mut dyn Storage,
+        dst: &Self,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.snapshot_to(storage, &dst.tree)?;
+
+        let nodes = self
+            .nodes
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (key, node) in nodes {
+            dst.nodes.save(storage, key, &node)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reset the leaf at `index` back to the default leaf and recompute its path, return the updated root.
+    /// The slot stays inserted, the next leaf is still inserted after the latest index.
+    /// Return the latest root without any change if the leaf is already the default leaf.
+    pub fn remove(
+        &self,
+        storage: &mut dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        let leaf = self
+            .tree
+            .get_leaf(storage, index)?
+            .ok_or(MerkleTreeError::LeafNotFound { index })?;
+        let default_leaf = self.tree.default_leaf(storage)?;
+
+        if leaf == default_leaf {
+            return self.tree.get_latest_root(storage);
+        }
+
+        let start_index = self.tree.start_index.may_load(storage)?.unwrap_or_default();
+        let last_position = self.tree.leaf_count(storage)? - 1;
+        let level = self.tree.level.load(storage)?;
+        let zeros = self.tree.load_zeros(storage)?;
+        let mut nodes = vec![];
+        let mut frontier = vec![];
+        let mut cur_hash = default_leaf.clone();
+        let mut cur_idx = index - start_index;
+
+        for (i, zero) in zeros.iter().enumerate().take(level as usize) {
+            // The frontier holds the latest left node of each level
+            if cur_idx == (last_position >> i) & !1 {
+                frontier.push((i as u8, cur_hash.clone()));
+            }
+
+            let sibling = self
+                .nodes
+                .may_load(storage, (i as u8, cur_idx ^ 1))?
+                .unwrap_or_else(|| zero.clone());
+            nodes.push(((i as u8, cur_idx), cur_hash.clone()));
+            cur_hash = match cur_idx.is_multiple_of(2) {
+                true => hasher.hash_two_owned(cur_hash, sibling)?,
+                false => hasher.hash_two_owned(sibling, cur_hash)?,
+            };
+            cur_idx /= 2;
+        }
+
+        self.tree.leafs.save(storage, index, &default_leaf)?;
+        for (key, node) in nodes {
+            self.nodes.save(storage, key, &node)?;
+        }
+        for (i, node) in frontier {
+            self.tree.hashes.save(storage, i, &node)?;
+        }
+        self.tree.root.save(storage, &cur_hash)?;
+
+        Ok(cur_hash)
+    }
+
+    /// Recompute every stored node and the root with the `new_hasher` like [SparseMerkleTree::rehash], return the new root.
+    /// Proofs generated before the migration are no longer valid, regenerate them from the rehashed tree.
+    pub fn rehash(
+        &self,
+        storage: &mut dyn Storage,
+        old_hasher: &H,
+        new_hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        let root = self.tree.rehash(storage, old_hasher, new_hasher)?;
+        let leafs = self
+            .tree
+            .leafs
+            .range(storage, None, None, Order::Ascending)
+            .map(|e| e.map(|(_, leaf)| leaf))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        self.nodes.clear(storage);
+        for (position, leaf) in leafs.into_iter().enumerate() {
+            self.save_nodes(storage, position as u64, leaf, new_hasher)?;
+        }
+
+        Ok(root)
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert_returning_proof] and save the nodes on its path.
+    /// The path is hashed once by the tree before anything is written.
+    fn insert_with_nodes(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        let insert = self.tree.prepare_insert(storage, leaf, hasher)?;
+
+        self.tree.apply_insert(storage, &insert)?;
+        self.tree.root.save(storage, &insert.root)?;
+        for (i, node) in insert.path.iter().enumerate() {
+            let cur_idx = insert.proof.index.checked_shr(i as u32).unwrap_or_default();
+            self.nodes.save(storage, (i as u8, cur_idx), node)?;
+        }
+
+        Ok((insert.index, insert.root, insert.proof))
+    }
+
+    /// Save the `leaf` at `position` and all of its ancestors below the root.
+    fn save_nodes(
+        &self,
+        storage: &mut dyn Storage,
+        position: u64,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        let level = self.tree.level.load(storage)?;
+        let zeros = self.tree.load_zeros(storage)?;
+        let mut cur_hash = leaf;
+        let mut cur_idx = position;
+
+        for (i, zero) in zeros.iter().enumerate().take(level as usize) {
+            let i = i as u8;
+
+            self.nodes.save(storage, (i, cur_idx), &cur_hash)?;
+
+            // The parent of the top level is the root, which is already saved by the tree
+            if i + 1 == level {
+                break;
+            }
+
+            let sibling = self
+                .nodes
+                .may_load(storage, (i, cur_idx ^ 1))?
+                .unwrap_or_else(|| zero.clone());
+            cur_hash = match cur_idx.is_multiple_of(2) {
+                true => hasher.hash_two_owned(cur_hash, sibling)?,
+                false => hasher.hash_two_owned(sibling, cur_hash)?,
+            };
+            cur_idx /= 2;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq, H: Hasher<L>> MerkleTree<L, H>
+    for SparseMerkleTreeFullNode<'a, L, H>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.init(storage, level, default_leaf, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        self.tree.is_valid_root(storage, root)
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.roots_count(storage)
+    }
+
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        let (index, latest_root, _) = self.insert_with_nodes(storage, leaf, hasher)?;
+
+        Ok((index, latest_root))
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        self.insert_with_nodes(storage, leaf, hasher)
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        let proof = self.gen_proof(storage, index)?;
+        let leaf = self
+            .tree
+            .get_leaf(storage, index)?
+            .ok_or(MerkleTreeError::LeafNotFound { index })?;
+
+        Ok(proof.verify(&self.tree.get_latest_root(storage)?, &leaf, hasher)?)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)?;
+        self.nodes.clear(storage);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, error::Error};
+
+    use cosmwasm_std::{testing::MockStorage, Order, Uint256};
+    use cw_storage_plus::Map;
+
+    use crate::{test_utils::Blake2, Hasher, HasherError, MerkleTree, MerkleTreeError};
+
+    use super::{SparseMerkleTree, SparseMerkleTreeFullNode};
+
+    const TREE: SparseMerkleTreeFullNode<Uint256, Blake2> =
+        SparseMerkleTreeFullNode::new("hashes", "leafs", "level", "root", "nodes");
+
+    #[test]
+    fn node_at() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=3)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let zeros = TREE.tree.load_zeros(&storage)?;
+
+        assert_eq!(TREE.node_at(&storage, 0, 1)?, leafs[1]);
+        assert_eq!(
+            TREE.node_at(&storage, 1, 0)?,
+            Blake2.hash_two(&leafs[0], &leafs[1])?
+        );
+        assert_eq!(
+            TREE.node_at(&storage, 1, 1)?,
+            Blake2.hash_two(&leafs[2], &zeros[0])?
+        );
+        assert_eq!(TREE.node_at(&storage, 2, 1)?, zeros[2]);
+        assert_eq!(
+            TREE.node_at(&storage, 20, 0)?,
+            TREE.get_latest_root(&storage)?
+        );
+        assert!(matches!(
+            TREE.node_at(&storage, 19, 2),
+            Err(MerkleTreeError::NodeOutOfRange {
+                level: 19,
+                index: 2
+            })
+        ));
+        assert!(matches!(
+            TREE.node_at(&storage, 21, 0),
+            Err(MerkleTreeError::NodeOutOfRange {
+                level: 21,
+                index: 0
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_hashes_path_once() -> Result<(), Box<dyn Error>> {
+        #[derive(Clone, Debug, Default)]
+        struct Counting(Cell<u32>);
+
+        impl Hasher<Uint256> for Counting {
+            fn hash_two(&self, left: &Uint256, right: &Uint256) -> Result<Uint256, HasherError> {
+                self.0.set(self.0.get() + 1);
+                Blake2.hash_two(left, right)
+            }
+        }
+
+        const COUNTING_TREE: SparseMerkleTreeFullNode<Uint256, Counting> =
+            SparseMerkleTreeFullNode::new("hashes", "leafs", "level", "root", "nodes");
+
+        let mut storage = MockStorage::new();
+        let hasher = Counting::default();
+
+        COUNTING_TREE.init(&mut storage, 4, Uint256::zero(), &hasher)?;
+
+        for i in 1..=3 {
+            hasher.0.set(0);
+            COUNTING_TREE.insert(&mut storage, Uint256::from_u128(i), &hasher)?;
+
+            assert_eq!(hasher.0.get(), 4);
+        }
+        for index in 0..3 {
+            assert_eq!(
+                COUNTING_TREE.gen_proof(&storage, index)?,
+                COUNTING_TREE.tree.gen_proof(&storage, index, &hasher)?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn on_insert_error() -> Result<(), Box<dyn Error>> {
+        const REJECTING_TREE: SparseMerkleTreeFullNode<Uint256, Blake2> =
+            SparseMerkleTreeFullNode {
+                tree: SparseMerkleTree::new("hashes", "leafs", "level", "root")
+                    .with_on_insert(|_, _, _| Err(MerkleTreeError::DuplicateLeaf)),
+                nodes: Map::new("nodes"),
+            };
+
+        let mut storage = MockStorage::new();
+
+        REJECTING_TREE.init(&mut storage, 4, Uint256::zero(), &Blake2)?;
+
+        assert!(matches!(
+            REJECTING_TREE.insert(&mut storage, Uint256::one(), &Blake2),
+            Err(MerkleTreeError::DuplicateLeaf)
+        ));
+        assert_eq!(REJECTING_TREE.leaf_count(&storage)?, 0);
+        assert!(REJECTING_TREE
+            .nodes
+            .range(&storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn gen_proof() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=5)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for leaf in leafs.iter() {
+            TREE.insert(&mut storage, *leaf, &Blake2)?;
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+
+        for (index, leaf) in leafs.iter().enumerate() {
+            let proof = TREE.gen_proof(&storage, index as u64)?;
+
+            assert_eq!(proof, TREE.tree.gen_proof(&storage, index as u64, &Blake2)?);
+            assert!(proof.verify(&root, leaf, &Blake2)?);
+            assert!(TREE.prove_and_verify(&storage, index as u64, &Blake2)?);
+        }
+        assert!(matches!(
+            TREE.gen_proof(&storage, 5),
+            Err(MerkleTreeError::LeafNotFound { index: 5 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn root_after_n_inserts() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let mut roots = vec![TREE.get_latest_root(&storage)?];
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            roots.push(TREE.insert(&mut storage, leaf, &Blake2)?.1);
+        }
+
+        for (n, root) in roots.iter().enumerate() {
+            assert_eq!(
+                &TREE
+                    .tree
+                    .root_after_n_inserts(&storage, n as u64, &Blake2)?,
+                root
+            );
+        }
+        assert!(matches!(
+            TREE.tree.root_after_n_inserts(&storage, 6, &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 5 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let mut expected_storage = MockStorage::new();
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+        TREE.init(&mut expected_storage, 20, default_leaf, &Blake2)?;
+
+        let leafs = (1..=4)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TREE.insert(&mut storage, leafs[0], &Blake2)?;
+        TREE.insert(&mut storage, leafs[1], &Blake2)?;
+
+        let root = TREE.get_latest_root(&storage)?;
+
+        TREE.insert(&mut storage, leafs[2], &Blake2)?;
+
+        assert_eq!(TREE.remove(&mut storage, 2, &Blake2)?, root);
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+        assert_eq!(TREE.get_leaf(&storage, 2)?, Some(default_leaf));
+        assert_eq!(TREE.remove(&mut storage, 2, &Blake2)?, root);
+        assert!(matches!(
+            TREE.remove(&mut storage, 3, &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 3 })
+        ));
+
+        // The next leaf still goes after the removed one, as if the default leaf was inserted
+        for leaf in [leafs[0], leafs[1], default_leaf, leafs[3]] {
+            TREE.insert(&mut expected_storage, leaf, &Blake2)?;
+        }
+
+        assert_eq!(TREE.insert(&mut storage, leafs[3], &Blake2)?.0, 3);
+        assert_eq!(
+            TREE.get_latest_root(&storage)?,
+            TREE.get_latest_root(&expected_storage)?
+        );
+        assert!(TREE.tree.verify_integrity(&storage, &Blake2)?);
+        assert_eq!(
+            TREE.node_at(&storage, 1, 1)?,
+            Blake2.hash_two(&default_leaf, &leafs[3])?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rehash() -> Result<(), Box<dyn Error>> {
+        /// Blake2 before the migration and Blake2 of the swapped pair after.
+        #[derive(Clone, Debug)]
+        struct Versioned(bool);
+
+        impl Hasher<Uint256> for Versioned {
+            fn hash_two(&self, left: &Uint256, right: &Uint256) -> Result<Uint256, HasherError> {
+                match self.0 {
+                    false => Blake2.hash_two(left, right),
+                    true => Blake2.hash_two(right, left),
+                }
+            }
+        }
+
+        const VERSIONED_TREE: SparseMerkleTreeFullNode<Uint256, Versioned> =
+            SparseMerkleTreeFullNode::new("hashes", "leafs", "level", "root", "nodes");
+
+        let mut storage = MockStorage::new();
+        let mut expected_storage = MockStorage::new();
+        let (old_hasher, new_hasher) = (Versioned(false), Versioned(true));
+        let default_leaf = Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?;
+
+        VERSIONED_TREE.init(&mut storage, 20, default_leaf, &old_hasher)?;
+        VERSIONED_TREE.init(&mut expected_storage, 20, default_leaf, &new_hasher)?;
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            VERSIONED_TREE.insert(&mut storage, leaf, &old_hasher)?;
+            VERSIONED_TREE.insert(&mut expected_storage, leaf, &new_hasher)?;
+        }
+
+        let old_root = VERSIONED_TREE.get_latest_root(&storage)?;
+        let old_proof = VERSIONED_TREE.tree.gen_proof(&storage, 1, &old_hasher)?;
+        let new_root = VERSIONED_TREE.rehash(&mut storage, &old_hasher, &new_hasher)?;
+
+        assert_ne!(new_root, old_root);
+        assert_eq!(new_root, VERSIONED_TREE.get_latest_root(&expected_storage)?);
+        assert_eq!(VERSIONED_TREE.get_latest_root(&storage)?, new_root);
+        assert_eq!(
+            VERSIONED_TREE.node_at(&storage, 1, 1)?,
+            VERSIONED_TREE.node_at(&expected_storage, 1, 1)?
+        );
+        assert!(VERSIONED_TREE.prove_and_verify(&storage, 1, &new_hasher)?);
+        assert!(!old_proof.verify(
+            &new_root,
+            &VERSIONED_TREE.get_leaf(&storage, 1)?.unwrap(),
+            &new_hasher
+        )?);
+
+        // The tree no longer matches the old hasher
+        assert!(matches!(
+            VERSIONED_TREE.rehash(&mut storage, &old_hasher, &new_hasher),
+            Err(MerkleTreeError::StorageCorruption(_))
+        ));
+
+        Ok(())
+    }
+}
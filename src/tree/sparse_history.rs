@@ -1,12 +1,12 @@
 use std::fmt::Debug;
 
-use cosmwasm_std::Empty;
-use cw_storage_plus::{Map, PrimaryKey};
+use cosmwasm_std::{Empty, Order, StdResult};
+use cw_storage_plus::{Bound, KeyDeserialize, Map, PrimaryKey};
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{Hasher, MerkleTree};
+use crate::{algorithm, Hasher, MerkleTree, StorageStats, TreeState};
 
-use super::SparseMerkleTree;
+use super::{KeyedMap, SparseMerkleTree, TreeNamespaces};
 
 /// Like [SparseMerkleTree] but able to check valid root hash with all previous root hashes.
 pub struct SparseMerkleTreeWithHistory<
@@ -15,7 +15,12 @@ pub struct SparseMerkleTreeWithHistory<
     H: Hasher<L>,
 > {
     pub tree: SparseMerkleTree<'a, L, H>,
+    /// Valid roots keyed by the root itself.
+    /// The key is the last part of the storage key, so distinct roots never collide even with different lengths.
     pub root_history: Map<'a, L, Empty>,
+    /// Saved root keyed by the leaf count at the time it is saved, used to prune the oldest roots.
+    /// Keyed under the root namespace, the roots saved before the index is kept are not indexed.
+    pub root_index: KeyedMap<'a, u64, L>,
 }
 
 impl<
@@ -34,8 +39,222 @@ impl<
         Self {
             tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
             root_history: Map::new(root_history_ns),
+            root_index: KeyedMap::new(root_ns, "root_index"),
         }
     }
+
+    /// Like [SparseMerkleTreeWithHistory::new] but with the tree namespaces set by name.
+    pub const fn from_namespaces(tree: TreeNamespaces<'a>, root_history_ns: &'a str) -> Self {
+        Self {
+            tree: SparseMerkleTree::from_namespaces(tree),
+            root_history: Map::new(root_history_ns),
+            root_index: KeyedMap::new(tree.meta, "root_index"),
+        }
+    }
+
+    /// Initialize the tree and insert all `leaves` like [SparseMerkleTree::init_with_leaves].
+    pub fn init_with_leaves(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        level: u8,
+        default_leaf: L,
+        leaves: Vec<L>,
+        hasher: &H,
+    ) -> Result<L, crate::MerkleTreeError> {
+        algorithm::count_fits(leaves.len() as u64, level as usize)
+            .then_some(())
+            .ok_or(crate::MerkleTreeError::ExceedMaxLeaf)?;
+
+        self.init(storage, level, default_leaf, hasher)?;
+
+        for leaf in leaves {
+            self.insert_no_root(storage, leaf, hasher)?;
+        }
+
+        self.commit_root(storage, hasher)
+    }
+
+    /// Export the full state of the tree like [SparseMerkleTree::export_state].
+    /// The root history is not included.
+    pub fn export_state(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+    ) -> Result<TreeState<L>, crate::MerkleTreeError> {
+        self.tree.export_state(storage)
+    }
+
+    /// Import the exported `state` like [SparseMerkleTree::import_state].
+    /// Only the imported latest root is saved into the root history.
+    pub fn import_state(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        state: TreeState<L>,
+        hasher: &H,
+    ) -> Result<(), crate::MerkleTreeError> {
+        let root = state.root.clone();
+
+        self.tree.import_state(storage, state, hasher)?;
+
+        if let Some(root) = root {
+            self.save_root_history(storage, &root)?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert the `leaf` like [SparseMerkleTree::insert_no_root].
+    /// The root history is only updated on [SparseMerkleTreeWithHistory::commit_root].
+    pub fn insert_no_root(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<u64, crate::MerkleTreeError> {
+        self.tree.insert_no_root(storage, leaf, hasher)
+    }
+
+    /// Compute and save the latest root into the root history.
+    pub fn commit_root(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        hasher: &H,
+    ) -> Result<L, crate::MerkleTreeError> {
+        let latest_root = self.tree.commit_root(storage, hasher)?;
+
+        self.save_root_history(storage, &latest_root)?;
+
+        Ok(latest_root)
+    }
+
+    /// Generate the proof of the leaf at `index` against the latest root like [SparseMerkleTree::gen_proof].
+    pub fn gen_proof(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<crate::MerkleProof<L>, crate::MerkleTreeError> {
+        self.tree.gen_proof(storage, index, hasher)
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert] then remove the oldest roots until at most `keep` roots remain.
+    /// The roots saved before the root index is kept are not indexed, they are neither counted nor pruned.
+    pub fn insert_retaining(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        leaf: L,
+        hasher: &H,
+        keep: usize,
+    ) -> Result<(u64, L), crate::MerkleTreeError> {
+        let (index, latest_root) = self.insert(storage, leaf, hasher)?;
+        let leaf_count = self.tree.leaf_count(storage)?;
+
+        let pruned = self
+            .root_index
+            .range(
+                storage,
+                None,
+                Some(Bound::inclusive(leaf_count.saturating_sub(keep as u64))),
+                Order::Ascending,
+            )
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (count, root) in pruned {
+            self.root_index.remove(storage, count);
+            self.root_history.remove(storage, root);
+        }
+
+        Ok((index, latest_root))
+    }
+
+    /// Check if the `root` is valid like [MerkleTree::is_valid_root] and saved at most `max_age` inserts ago.
+    /// The age is counted with the leaf count, the latest root has the age of 0.
+    /// The roots saved before the root index is kept have no known age and are never valid within `max_age`.
+    pub fn is_valid_root_within(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+        root: &L,
+        max_age: u64,
+    ) -> Result<bool, crate::MerkleTreeError> {
+        let leaf_count = self.tree.leaf_count(storage)?;
+
+        for item in self.root_index.range(
+            storage,
+            Some(Bound::inclusive(leaf_count.saturating_sub(max_age))),
+            None,
+            Order::Ascending,
+        ) {
+            if &item?.1 == root {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Deep copy the tree like [SparseMerkleTree::snapshot_to] together with the root history.
+    pub fn snapshot_to(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        dst: &Self,
+    ) -> Result<(), crate::MerkleTreeError>
+    where
+        L: KeyDeserialize<Output = L> + 'static,
+    {
+        self.tree.snapshot_to(storage, &dst.tree)?;
+
+        let roots = self
+            .root_history
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for root in roots {
+            dst.root_history.save(storage, root, &Empty {})?;
+        }
+
+        let indexed_roots = self
+            .root_index
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (count, root) in indexed_roots {
+            dst.root_index.save(storage, count, &root)?;
+        }
+
+        Ok(())
+    }
+
+    /// Count the stored leaves and roots like [SparseMerkleTree::storage_stats].
+    pub fn storage_stats(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+    ) -> Result<StorageStats, crate::MerkleTreeError> {
+        let stats = self.tree.storage_stats(storage)?;
+        let history_roots = self
+            .root_history
+            .keys_raw(storage, None, None, Order::Ascending)
+            .count() as u64;
+
+        Ok(StorageStats {
+            history_roots,
+            max_history_roots: None,
+            estimated_bytes: stats.estimated_bytes
+                + 2 * history_roots * self.tree.node_bytes(storage)?,
+            ..stats
+        })
+    }
+
+    fn save_root_history(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        root: &L,
+    ) -> Result<(), crate::MerkleTreeError> {
+        let leaf_count = self.tree.leaf_count(storage)?;
+
+        self.root_history.save(storage, root.clone(), &Empty {})?;
+        self.root_index.save(storage, leaf_count, root)?;
+
+        Ok(())
+    }
 }
 
 impl<
@@ -62,6 +281,16 @@ impl<
         Ok(self.root_history.has(storage, root.clone()))
     }
 
+    fn roots_count(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+    ) -> Result<u64, crate::MerkleTreeError> {
+        Ok(self
+            .root_history
+            .keys_raw(storage, None, None, cosmwasm_std::Order::Ascending)
+            .count() as u64)
+    }
+
     fn insert(
         &self,
         storage: &mut dyn cosmwasm_std::Storage,
@@ -70,32 +299,96 @@ impl<
     ) -> Result<(u64, L), crate::MerkleTreeError> {
         let (index, latest_root) = self.tree.insert(storage, leaf, hasher)?;
 
-        self.root_history
-            .save(storage, latest_root.clone(), &Empty {})?;
+        self.save_root_history(storage, &latest_root)?;
 
         Ok((index, latest_root))
     }
 
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn cosmwasm_std::Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, crate::MerkleProof<L>), crate::MerkleTreeError> {
+        let (index, latest_root, proof) =
+            self.tree.insert_returning_proof(storage, leaf, hasher)?;
+
+        self.save_root_history(storage, &latest_root)?;
+
+        Ok((index, latest_root, proof))
+    }
+
     fn get_latest_root(
         &self,
         storage: &dyn cosmwasm_std::Storage,
     ) -> Result<L, crate::MerkleTreeError> {
         self.tree.get_latest_root(storage)
     }
+
+    fn get_leaf(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+        index: u64,
+    ) -> Result<Option<L>, crate::MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn cosmwasm_std::Storage) -> Result<u8, crate::MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+    ) -> Result<L, crate::MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn cosmwasm_std::Storage) -> Result<Vec<L>, crate::MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+    ) -> Result<u64, crate::MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn cosmwasm_std::Storage) -> Result<bool, crate::MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn cosmwasm_std::Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, crate::MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn cosmwasm_std::Storage) -> Result<(), crate::MerkleTreeError> {
+        self.tree.reset(storage)?;
+        self.root_history.clear(storage);
+        self.root_index.clear(storage)?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::error::Error;
 
-    use cosmwasm_std::{testing::MockStorage, Uint256};
+    use cosmwasm_std::{testing::MockStorage, Empty, Uint256};
 
-    use crate::{test_utils::Blake2, Hasher, MerkleTree};
+    use crate::{test_utils::Blake2, Hasher, MerkleTree, MerkleTreeError};
 
     use super::SparseMerkleTreeWithHistory;
 
     const TREE: SparseMerkleTreeWithHistory<Vec<u8>, Blake2> =
-        SparseMerkleTreeWithHistory::new("hashes", "leafs", "level", "zeros", "root_history");
+        SparseMerkleTreeWithHistory::new("hashes", "leafs", "level", "root", "root_history");
     const ZERO: [u8; 32] = [
         0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         0, 0,
@@ -168,6 +461,127 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn snapshot_to() -> Result<(), Box<dyn Error>> {
+        const SNAPSHOT_TREE: SparseMerkleTreeWithHistory<Vec<u8>, Blake2> =
+            SparseMerkleTreeWithHistory::new(
+                "snapshot_hashes",
+                "snapshot_leafs",
+                "snapshot_level",
+                "snapshot_root",
+                "snapshot_root_history",
+            );
+
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+        let two_vec = Uint256::from_u128(2).to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let (_, old_root) =
+            TREE.insert(&mut storage, Blake2.hash_two(&one_vec, &one_vec)?, &Blake2)?;
+
+        TREE.snapshot_to(&mut storage, &SNAPSHOT_TREE)?;
+
+        let (_, new_root) =
+            TREE.insert(&mut storage, Blake2.hash_two(&two_vec, &two_vec)?, &Blake2)?;
+
+        assert!(SNAPSHOT_TREE.is_valid_root(&storage, &old_root)?);
+        assert!(!SNAPSHOT_TREE.is_valid_root(&storage, &new_root)?);
+        assert_eq!(SNAPSHOT_TREE.leaf_count(&storage)?, 1);
+        assert_eq!(TREE.leaf_count(&storage)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_retaining() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+        let mut roots = vec![];
+
+        for _ in 0..6 {
+            roots.push(
+                TREE.insert_retaining(&mut storage, leaf.clone(), &Blake2, 2)?
+                    .1,
+            );
+        }
+
+        for root in roots[..4].iter() {
+            assert!(!TREE.is_valid_root(&storage, root)?);
+        }
+        for root in roots[4..].iter() {
+            assert!(TREE.is_valid_root(&storage, root)?);
+        }
+        assert_eq!(TREE.storage_stats(&storage)?.history_roots, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn roots_before_root_index() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+        let (_, legacy_root) = TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+
+        // A root saved before the root index is kept
+        TREE.root_index.clear(&mut storage)?;
+
+        assert!(TREE.is_valid_root(&storage, &legacy_root)?);
+        assert!(!TREE.is_valid_root_within(&storage, &legacy_root, 0)?);
+
+        for _ in 0..3 {
+            TREE.insert_retaining(&mut storage, leaf.clone(), &Blake2, 1)?;
+        }
+
+        assert!(TREE.is_valid_root(&storage, &legacy_root)?);
+        assert_eq!(TREE.storage_stats(&storage)?.history_roots, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn root_history_key() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let root = vec![1, 2, 3];
+
+        TREE.root_history
+            .save(&mut storage, root.clone(), &Empty {})?;
+
+        assert!(TREE.is_valid_root(&storage, &root)?);
+        assert!(!TREE.is_valid_root(&storage, &vec![1, 2, 3, 0])?);
+        assert!(!TREE.is_valid_root(&storage, &vec![1, 2])?);
+        assert!(!TREE.is_valid_root(&storage, &vec![0, 1, 2, 3])?);
+
+        Ok(())
+    }
+
     #[test]
     fn root_history() -> Result<(), Box<dyn Error>> {
         let mut storage = MockStorage::new();
@@ -191,4 +605,129 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn validate_roots() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+
+        let (_, old_root) = TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+        let (_, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(
+            TREE.validate_roots(&storage, &[old_root, vec![1, 2, 3], new_root, zero_vec])?,
+            vec![true, false, true, false]
+        );
+        assert_eq!(TREE.validate_roots(&storage, &[])?, Vec::<bool>::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn roots_count() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.roots_count(&storage)?, 0);
+
+        for i in 1..=8u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            TREE.insert(
+                &mut storage,
+                Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+                &Blake2,
+            )?;
+
+            assert_eq!(TREE.roots_count(&storage)?, i as u64);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_valid_root_within() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let mut roots = vec![];
+        for i in 1..=4u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            let (_, root) = TREE.insert(
+                &mut storage,
+                Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+                &Blake2,
+            )?;
+            roots.push(root);
+        }
+
+        assert!(TREE.is_valid_root_within(&storage, &roots[3], 0)?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[2], 0)?);
+        assert!(TREE.is_valid_root_within(&storage, &roots[1], 2)?);
+
+        // The root is still in the history but too old
+        assert!(TREE.is_valid_root(&storage, &roots[0])?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[0], 2)?);
+        assert!(TREE.is_valid_root_within(&storage, &roots[0], 3)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn gen_proof() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let mut leafs = vec![];
+        for i in 1..=3u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            let leaf = Blake2.hash_two(&leaf_vec, &leaf_vec)?;
+            TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+            leafs.push(leaf);
+        }
+
+        let root = TREE.get_latest_root(&storage)?;
+
+        for (index, leaf) in leafs.iter().enumerate() {
+            let proof = TREE.gen_proof(&storage, index as u64, &Blake2)?;
+
+            assert!(proof.verify(&root, leaf, &Blake2)?);
+        }
+        assert!(matches!(
+            TREE.gen_proof(&storage, 3, &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 3 })
+        ));
+
+        Ok(())
+    }
 }
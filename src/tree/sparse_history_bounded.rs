@@ -1,23 +1,25 @@
 use std::fmt::Debug;
 
-use cosmwasm_std::{Empty, Order, Storage};
-use cw_storage_plus::{Bound, Item, Map, PrimaryKey};
+use cosmwasm_std::{attr, Attribute, Order, Storage};
+use cw_storage_plus::{Bound, PrimaryKey};
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{Hasher, MerkleTree, MerkleTreeError};
+use crate::{
+    algorithm, leaf_to_hex, Hasher, LeafBytes, MerkleProof, MerkleTree, MerkleTreeError,
+    StorageStats, TreeState,
+};
 
-use super::SparseMerkleTree;
+use super::{BoundedHistoryNamespaces, RootHistory, SparseMerkleTree, TreeNamespaces};
 
 /// Like [SparseMerkleTree] but able to check valid root hash with previous root hashes upto specified history level.
+/// `HISTORY_LEVEL` must be greater than zero, the tree of zero history level fails to initialize.
 pub struct SparseMerkleTreeWithHistoryBounded<
     'a,
     L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + PrimaryKey<'a>,
     H: Hasher<L>,
     const HISTORY_LEVEL: u32,
 > {
-    pub history_index: Item<'a, u32>,
-    pub root_history: Map<'a, L, Empty>,
-    pub root_index: Map<'a, u32, L>,
+    pub history: RootHistory<'a, L>,
     pub tree: SparseMerkleTree<'a, L, H>,
 }
 
@@ -38,20 +40,48 @@ impl<
         history_index_ns: &'a str,
     ) -> Self {
         Self {
-            history_index: Item::new(history_index_ns),
-            root_history: Map::new(root_history_ns),
-            root_index: Map::new(root_index_ns),
+            history: RootHistory::new(root_history_ns, root_index_ns, history_index_ns),
             tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
         }
     }
 
+    /// Like [SparseMerkleTreeWithHistoryBounded::new] but with the namespaces set by name.
+    pub const fn from_namespaces(
+        tree: TreeNamespaces<'a>,
+        history: BoundedHistoryNamespaces<'a>,
+    ) -> Self {
+        Self {
+            history: RootHistory::from_namespaces(history),
+            tree: SparseMerkleTree::from_namespaces(tree),
+        }
+    }
+
+    /// Pin the roots still referenced like [RootHistory::with_can_evict].
+    pub const fn with_can_evict(
+        self,
+        can_evict: fn(&dyn Storage, &L) -> Result<bool, MerkleTreeError>,
+    ) -> Self {
+        Self {
+            history: self.history.with_can_evict(can_evict),
+            ..self
+        }
+    }
+
     /// Remove storage unused and out of range stored root.
     /// The removed root might not be the earliest.
     pub fn update_history_level(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
-        let updated_idx = self.history_index.may_load(storage)?.unwrap_or_default() % HISTORY_LEVEL;
-        self.history_index.save(storage, &updated_idx)?;
+        Self::check_history_level()?;
+
+        let updated_idx = self
+            .history
+            .history_index
+            .may_load(storage)?
+            .unwrap_or_default()
+            % HISTORY_LEVEL;
+        self.history.history_index.save(storage, &updated_idx)?;
 
         let mut root_range = self
+            .history
             .root_index
             .range(
                 storage,
@@ -62,12 +92,187 @@ impl<
             .collect::<Vec<_>>()
             .into_iter();
         while let Some(Ok((idx, root))) = root_range.next() {
-            self.root_index.remove(storage, idx);
-            self.root_history.remove(storage, root);
+            self.history.root_index.remove(storage, idx);
+            self.history.root_history.remove(storage, root);
+        }
+
+        Ok(())
+    }
+
+    /// Initialize the tree and insert all `leaves` like [SparseMerkleTree::init_with_leaves].
+    pub fn init_with_leaves(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        leaves: Vec<L>,
+        hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        algorithm::count_fits(leaves.len() as u64, level as usize)
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+
+        self.init(storage, level, default_leaf, hasher)?;
+
+        for leaf in leaves {
+            self.insert_no_root(storage, leaf, hasher)?;
+        }
+
+        self.commit_root(storage, hasher)
+    }
+
+    /// Export the full state of the tree like [SparseMerkleTree::export_state].
+    /// The root history is not included.
+    pub fn export_state(&self, storage: &dyn Storage) -> Result<TreeState<L>, MerkleTreeError> {
+        self.tree.export_state(storage)
+    }
+
+    /// Import the exported `state` like [SparseMerkleTree::import_state].
+    /// Only the imported latest root is saved into the root history.
+    pub fn import_state(
+        &self,
+        storage: &mut dyn Storage,
+        state: TreeState<L>,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        Self::check_history_level()?;
+
+        let root = state.root.clone();
+        let slot = self.history.next_slot(storage, HISTORY_LEVEL)?;
+
+        self.tree.import_state(storage, state, hasher)?;
+
+        if let Some(root) = root {
+            self.history.save(storage, slot, &root)?;
         }
 
         Ok(())
     }
+
+    /// Insert the `leaf` like [SparseMerkleTree::insert_no_root].
+    /// The root history is only updated on [SparseMerkleTreeWithHistoryBounded::commit_root].
+    pub fn insert_no_root(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<u64, MerkleTreeError> {
+        let total_inserts = self.history.next_total_inserts(storage)?;
+        let index = self.tree.insert_no_root(storage, leaf, hasher)?;
+        self.history.save_total_inserts(storage, total_inserts)?;
+
+        Ok(index)
+    }
+
+    /// Compute and save the latest root into the root history.
+    pub fn commit_root(&self, storage: &mut dyn Storage, hasher: &H) -> Result<L, MerkleTreeError> {
+        Self::check_history_level()?;
+
+        let slot = self.history.next_slot(storage, HISTORY_LEVEL)?;
+        let latest_root = self.tree.commit_root(storage, hasher)?;
+
+        self.history.save(storage, slot, &latest_root)?;
+
+        Ok(latest_root)
+    }
+
+    /// Deep copy the tree like [SparseMerkleTree::snapshot_to] together with the root history.
+    pub fn snapshot_to(
+        &self,
+        storage: &mut dyn Storage,
+        dst: &Self,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.snapshot_to(storage, &dst.tree)?;
+        self.history.snapshot_to(storage, &dst.history)?;
+
+        Ok(())
+    }
+
+    /// Generate the proof of the leaf at `index` against the retained `root` like [SparseMerkleTree::gen_proof_at_root].
+    /// Return [MerkleTreeError::RootNotFound] if the `root` is not retained in the root history.
+    pub fn gen_proof_at_root(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        root: &L,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
+        self.history
+            .is_valid_root(storage, root)
+            .then_some(())
+            .ok_or(MerkleTreeError::RootNotFound)?;
+
+        self.tree.gen_proof_at_root(storage, index, root, hasher)
+    }
+
+    /// Get the oldest root retained in the root history, which is the next root to be evicted.
+    /// Return `None` if no root is saved yet.
+    pub fn oldest_valid_root(&self, storage: &dyn Storage) -> Result<Option<L>, MerkleTreeError> {
+        Self::check_history_level()?;
+
+        self.history.oldest_valid_root(storage, HISTORY_LEVEL)
+    }
+
+    /// Count the stored leaves and roots like [SparseMerkleTree::storage_stats].
+    /// At most `HISTORY_LEVEL` roots are retained.
+    pub fn storage_stats(&self, storage: &dyn Storage) -> Result<StorageStats, MerkleTreeError> {
+        let stats = self.tree.storage_stats(storage)?;
+        let history_roots = self.history.slots_count(storage);
+
+        // Each root is stored in both the root history and the root index
+        Ok(StorageStats {
+            history_roots,
+            max_history_roots: Some(HISTORY_LEVEL as u64),
+            estimated_bytes: stats.estimated_bytes
+                + 2 * history_roots * self.tree.node_bytes(storage)?,
+            ..stats
+        })
+    }
+
+    /// Get the number of inserted leaves since initialization.
+    /// The counter keeps growing past the history level and is kept across [MerkleTree::reset].
+    pub fn total_inserts(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.history.total_inserts(storage)
+    }
+
+    /// Check if the `root` is valid like [MerkleTree::is_valid_root] and saved at most `max_age` inserts ago.
+    /// The age is counted with [SparseMerkleTreeWithHistoryBounded::total_inserts], the latest root has the age of 0.
+    /// Roots pinned by [SparseMerkleTreeWithHistoryBounded::with_can_evict] keep the age of when they are saved.
+    pub fn is_valid_root_within(
+        &self,
+        storage: &dyn Storage,
+        root: &L,
+        max_age: u64,
+    ) -> Result<bool, MerkleTreeError> {
+        self.history.is_valid_root_within(storage, root, max_age)
+    }
+
+    /// Return [MerkleTreeError::InvalidHistoryLevel] if `HISTORY_LEVEL` is zero, the history index is taken modulo it.
+    fn check_history_level() -> Result<(), MerkleTreeError> {
+        (HISTORY_LEVEL > 0)
+            .then_some(())
+            .ok_or(MerkleTreeError::InvalidHistoryLevel)
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert_returning_proof], also return the root evicted from the root history if any.
+    fn insert_evicting(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>, Option<L>), MerkleTreeError> {
+        Self::check_history_level()?;
+
+        let total_inserts = self.history.next_total_inserts(storage)?;
+        let slot = self.history.next_slot(storage, HISTORY_LEVEL)?;
+        let (index, latest_root, proof) =
+            self.tree.insert_returning_proof(storage, leaf, hasher)?;
+
+        self.history.save_total_inserts(storage, total_inserts)?;
+        let evicted_root = self.history.save(storage, slot, &latest_root)?;
+
+        Ok((index, latest_root, proof, evicted_root))
+    }
 }
 
 impl<
@@ -84,11 +289,17 @@ impl<
         default_leaf: L,
         hasher: &H,
     ) -> Result<(), MerkleTreeError> {
+        Self::check_history_level()?;
+
         self.tree.init(storage, level, default_leaf, hasher)
     }
 
     fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
-        Ok(self.root_history.has(storage, root.clone()))
+        Ok(self.history.is_valid_root(storage, root))
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        Ok(self.history.roots_count(storage))
     }
 
     fn insert(
@@ -97,38 +308,98 @@ impl<
         leaf: L,
         hasher: &H,
     ) -> Result<(u64, L), MerkleTreeError> {
-        let (index, latest_root) = self.tree.insert(storage, leaf, hasher)?;
-        let cur_idx = self.history_index.may_load(storage)?.unwrap_or_default();
-        let next_idx = (cur_idx + 1) % HISTORY_LEVEL;
+        let (index, latest_root, _, _) = self.insert_evicting(storage, leaf, hasher)?;
 
-        // Remove old root
-        if let Some(root) = self.root_index.may_load(storage, next_idx)? {
-            self.root_history.remove(storage, root);
-        }
+        Ok((index, latest_root))
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        let (index, latest_root, proof, _) = self.insert_evicting(storage, leaf, hasher)?;
 
-        // Insert new root
-        self.root_history
-            .save(storage, latest_root.clone(), &Empty {})?;
-        self.root_index.save(storage, next_idx, &latest_root)?;
+        Ok((index, latest_root, proof))
+    }
 
-        // Update current index
-        self.history_index.save(storage, &next_idx)?;
+    /// Insert the `leaf` like [MerkleTree::insert_with_event].
+    /// Also return the attribute of the hex-encoded root evicted from the root history once the history wraps.
+    fn insert_with_event(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, Vec<Attribute>), MerkleTreeError>
+    where
+        L: LeafBytes,
+    {
+        let (index, root, _, evicted_root) = self.insert_evicting(storage, leaf, hasher)?;
+        let mut attributes = vec![
+            attr("leaf_index", index.to_string()),
+            attr("root", leaf_to_hex(&root)),
+        ];
+        if let Some(evicted_root) = evicted_root {
+            attributes.push(attr("evicted_root", leaf_to_hex(&evicted_root)));
+        }
 
-        Ok((index, latest_root))
+        Ok((index, root, attributes))
     }
 
     fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
         self.tree.get_latest_root(storage)
     }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)?;
+        self.history.clear(storage)?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::error::Error;
 
-    use cosmwasm_std::{testing::MockStorage, Uint256};
+    use cosmwasm_std::{attr, testing::MockStorage, to_vec, Empty, Order, Uint256};
+    use cw_storage_plus::Map;
 
-    use crate::{test_utils::Blake2, Hasher, MerkleTree};
+    use crate::{leaf_to_hex, test_utils::Blake2, Hasher, MerkleTree, MerkleTreeError};
 
     use super::SparseMerkleTreeWithHistoryBounded;
 
@@ -214,6 +485,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn insert_with_event() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(&mut storage, 20, ZERO.to_vec(), &Blake2)?;
+
+        let mut roots = vec![];
+        for i in 1..=5 {
+            let leaf = Uint256::from_u128(i).to_be_bytes().to_vec();
+            let (index, root, attributes) = TREE.insert_with_event(&mut storage, leaf, &Blake2)?;
+
+            // The history is not full yet, nothing is evicted
+            assert_eq!(
+                attributes,
+                vec![
+                    attr("leaf_index", index.to_string()),
+                    attr("root", leaf_to_hex(&root)),
+                ]
+            );
+            roots.push(root);
+        }
+
+        let leaf = Uint256::from_u128(6).to_be_bytes().to_vec();
+        let (index, root, attributes) = TREE.insert_with_event(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(
+            attributes,
+            vec![
+                attr("leaf_index", index.to_string()),
+                attr("root", leaf_to_hex(&root)),
+                attr("evicted_root", leaf_to_hex(&roots[0])),
+            ]
+        );
+        assert!(!TREE.is_valid_root(&storage, &roots[0])?);
+        assert!(TREE.is_valid_root(&storage, &roots[1])?);
+
+        Ok(())
+    }
+
     #[test]
     fn root_history() -> Result<(), Box<dyn Error>> {
         let mut storage = MockStorage::new();
@@ -242,4 +552,525 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn can_evict() -> Result<(), Box<dyn Error>> {
+        const PINNED: Map<Vec<u8>, Empty> = Map::new("pinned");
+
+        const PINNED_TREE: SparseMerkleTreeWithHistoryBounded<Vec<u8>, Blake2, 3> =
+            SparseMerkleTreeWithHistoryBounded::new(
+                "hashes",
+                "leafs",
+                "level",
+                "root",
+                "root_history",
+                "root_index",
+                "history_index",
+            )
+            .with_can_evict(|storage, root| Ok(!PINNED.has(storage, root.clone())));
+
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+
+        PINNED_TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let (_, pinned_root) = PINNED_TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+        PINNED.save(&mut storage, pinned_root.clone(), &Empty {})?;
+
+        let mut roots = vec![];
+        for _ in 0..10 {
+            roots.push(PINNED_TREE.insert(&mut storage, leaf.clone(), &Blake2)?.1);
+        }
+
+        // The pinned root keeps its slot, the other two slots hold the latest roots
+        assert!(PINNED_TREE.is_valid_root(&storage, &pinned_root)?);
+        for root in &roots[..8] {
+            assert!(!PINNED_TREE.is_valid_root(&storage, root)?);
+        }
+        for root in &roots[8..] {
+            assert!(PINNED_TREE.is_valid_root(&storage, root)?);
+        }
+        assert_eq!(PINNED_TREE.roots_count(&storage)?, 3);
+        assert_eq!(PINNED_TREE.storage_stats(&storage)?.history_roots, 3);
+
+        // Nothing is written once every root is pinned
+        for root in &roots[8..] {
+            PINNED.save(&mut storage, root.clone(), &Empty {})?;
+        }
+        assert!(matches!(
+            PINNED_TREE.insert(&mut storage, leaf.clone(), &Blake2),
+            Err(MerkleTreeError::HistoryPinned)
+        ));
+        assert_eq!(PINNED_TREE.leaf_count(&storage)?, 11);
+        assert_eq!(PINNED_TREE.get_latest_root(&storage)?, roots[9]);
+
+        // Unpinning a root frees its slot again
+        PINNED.remove(&mut storage, pinned_root.clone());
+        PINNED_TREE.insert(&mut storage, leaf, &Blake2)?;
+        assert!(!PINNED_TREE.is_valid_root(&storage, &pinned_root)?);
+        assert!(PINNED_TREE.is_valid_root(&storage, &roots[8])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn oldest_valid_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.oldest_valid_root(&storage)?, None);
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+        let mut roots = vec![];
+
+        for _ in 0..3 {
+            roots.push(TREE.insert(&mut storage, leaf.clone(), &Blake2)?.1);
+        }
+
+        assert_eq!(TREE.oldest_valid_root(&storage)?, Some(roots[0].clone()));
+
+        for _ in 0..5 {
+            roots.push(TREE.insert(&mut storage, leaf.clone(), &Blake2)?.1);
+        }
+
+        assert_eq!(TREE.oldest_valid_root(&storage)?, Some(roots[3].clone()));
+        assert!(!TREE.is_valid_root(&storage, &roots[2])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn commit_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+
+        TREE.insert_no_root(&mut storage, leaf.clone(), &Blake2)?;
+        TREE.insert_no_root(&mut storage, leaf, &Blake2)?;
+
+        let root = TREE.commit_root(&mut storage, &Blake2)?;
+
+        assert_eq!(
+            root,
+            [
+                69, 102, 154, 15, 149, 187, 157, 26, 123, 248, 50, 67, 177, 207, 6, 143, 94, 80,
+                242, 17, 127, 26, 94, 197, 222, 220, 255, 245, 136, 20, 62, 132
+            ]
+        );
+        assert!(TREE.is_valid_root(&storage, &root)?);
+        assert_eq!(
+            TREE.history
+                .root_index
+                .range(&storage, None, None, Order::Ascending)
+                .count(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reset() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+        let default_leaf = Blake2.hash_two(&zero_vec, &zero_vec)?;
+
+        TREE.init(&mut storage, 10, default_leaf.clone(), &Blake2)?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+        let (_, old_root) = TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+
+        TREE.reset(&mut storage)?;
+
+        assert!(!TREE.is_valid_root(&storage, &old_root)?);
+        assert!(TREE.history.root_index.is_empty(&storage));
+        assert!(TREE.history.history_index.may_load(&storage)?.is_none());
+
+        TREE.init(&mut storage, 20, default_leaf, &Blake2)?;
+
+        let (index, new_root) = TREE.insert(&mut storage, leaf, &Blake2)?;
+
+        assert_eq!(index, 0);
+        assert_eq!(
+            new_root,
+            [
+                144, 77, 181, 73, 235, 223, 13, 204, 30, 18, 199, 252, 182, 160, 89, 248, 240, 219,
+                173, 150, 189, 114, 165, 70, 40, 159, 110, 9, 165, 185, 17, 229
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn storage_stats() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        for i in 1..=8u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            TREE.insert(
+                &mut storage,
+                Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+                &Blake2,
+            )?;
+        }
+
+        let stats = TREE.storage_stats(&storage)?;
+        let node_bytes = to_vec(&TREE.get_latest_root(&storage)?)?.len() as u64;
+
+        assert_eq!(stats.leaves, 8);
+        assert_eq!(stats.history_roots, 5);
+        assert_eq!(stats.max_history_roots, Some(5));
+        assert_eq!(stats.estimated_bytes, (8 + 41 + 2 * 5) * node_bytes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn roots_count() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.roots_count(&storage)?, 0);
+
+        for i in 1..=8u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            TREE.insert(
+                &mut storage,
+                Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+                &Blake2,
+            )?;
+
+            assert_eq!(TREE.roots_count(&storage)?, i.min(5) as u64);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn gen_proof_at_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let mut leafs = vec![];
+        let mut roots = vec![];
+        for i in 1..=8u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            let leaf = Blake2.hash_two(&leaf_vec, &leaf_vec)?;
+            let (_, root) = TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+            leafs.push(leaf);
+            roots.push(root);
+        }
+
+        // The root after the 5th insert is retained but no longer the latest
+        let proof = TREE.gen_proof_at_root(&storage, 1, &roots[4], &Blake2)?;
+
+        assert!(proof.verify(&roots[4], &leafs[1], &Blake2)?);
+        assert!(!proof.verify(&roots[7], &leafs[1], &Blake2)?);
+        assert_eq!(
+            TREE.gen_proof_at_root(&storage, 7, &roots[7], &Blake2)?,
+            TREE.tree.gen_proof(&storage, 7, &Blake2)?
+        );
+        assert!(matches!(
+            TREE.gen_proof_at_root(&storage, 5, &roots[4], &Blake2),
+            Err(MerkleTreeError::LeafNotFound { index: 5 })
+        ));
+
+        // The root after the 3rd insert is evicted
+        assert!(matches!(
+            TREE.gen_proof_at_root(&storage, 1, &roots[2], &Blake2),
+            Err(MerkleTreeError::RootNotFound)
+        ));
+        assert!(TREE
+            .tree
+            .gen_proof_at_root(&storage, 1, &roots[2], &Blake2)?
+            .verify(&roots[2], &leafs[1], &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn total_inserts() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.total_inserts(&storage)?, 0);
+
+        for i in 1..=8u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            TREE.insert(
+                &mut storage,
+                Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+                &Blake2,
+            )?;
+
+            assert_eq!(TREE.total_inserts(&storage)?, i as u64);
+        }
+
+        // The history index wraps while the counter keeps growing
+        assert_eq!(TREE.history.history_index.load(&storage)?, 8 % 5);
+
+        TREE.insert_no_root(
+            &mut storage,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.total_inserts(&storage)?, 9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn total_inserts_before_upgrade() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let mut roots = vec![];
+        for i in 1..=3u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            let (_, root) = TREE.insert(
+                &mut storage,
+                Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+                &Blake2,
+            )?;
+            roots.push(root);
+        }
+
+        // A history saved before the counters are kept
+        TREE.history.total_inserts.remove(&mut storage);
+        TREE.history.root_inserts.clear(&mut storage)?;
+
+        assert_eq!(TREE.total_inserts(&storage)?, 3);
+        assert!(TREE.is_valid_root_within(&storage, &roots[1], 1)?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[0], 1)?);
+
+        let leaf_vec = Uint256::from_u128(4).to_be_bytes().to_vec();
+        TREE.insert(
+            &mut storage,
+            Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+            &Blake2,
+        )?;
+
+        // The counters are seeded before the history index moves
+        assert_eq!(TREE.total_inserts(&storage)?, 4);
+        assert!(TREE.is_valid_root_within(&storage, &roots[1], 2)?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[1], 1)?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[0], 2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn total_inserts_overflow() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+        TREE.history.total_inserts.save(&mut storage, &u64::MAX)?;
+
+        let root = TREE.get_latest_root(&storage)?;
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+
+        assert!(matches!(
+            TREE.insert(&mut storage, leaf.clone(), &Blake2),
+            Err(MerkleTreeError::Overflow)
+        ));
+        assert!(matches!(
+            TREE.insert_no_root(&mut storage, leaf, &Blake2),
+            Err(MerkleTreeError::Overflow)
+        ));
+
+        // Nothing is inserted
+        assert_eq!(TREE.leaf_count(&storage)?, 0);
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+        assert_eq!(TREE.total_inserts(&storage)?, u64::MAX);
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_valid_root_within() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let mut roots = vec![];
+        for i in 1..=4u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            let (_, root) = TREE.insert(
+                &mut storage,
+                Blake2.hash_two(&leaf_vec, &leaf_vec)?,
+                &Blake2,
+            )?;
+            roots.push(root);
+        }
+
+        assert!(TREE.is_valid_root_within(&storage, &roots[3], 0)?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[2], 0)?);
+        assert!(TREE.is_valid_root_within(&storage, &roots[0], 3)?);
+
+        // The root is still in the history but too old
+        assert!(TREE.is_valid_root(&storage, &roots[0])?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[0], 2)?);
+
+        // Batched leaves are counted as inserts even if only one root is committed
+        TREE.insert_no_root(&mut storage, roots[0].clone(), &Blake2)?;
+        TREE.insert_no_root(&mut storage, roots[1].clone(), &Blake2)?;
+        let root = TREE.commit_root(&mut storage, &Blake2)?;
+
+        assert!(TREE.is_valid_root_within(&storage, &root, 0)?);
+        assert!(TREE.is_valid_root_within(&storage, &roots[3], 2)?);
+        assert!(!TREE.is_valid_root_within(&storage, &roots[3], 1)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_inclusion() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let mut leafs = vec![];
+        let mut roots = vec![];
+        for i in 1..=8u128 {
+            let leaf_vec = Uint256::from_u128(i).to_be_bytes().to_vec();
+            let leaf = Blake2.hash_two(&leaf_vec, &leaf_vec)?;
+            let (_, root) = TREE.insert(&mut storage, leaf.clone(), &Blake2)?;
+            leafs.push(leaf);
+            roots.push(root);
+        }
+
+        // Proof against the latest root and against an older retained root
+        let proof = TREE.tree.gen_proof(&storage, 1, &Blake2)?;
+
+        assert!(TREE.check_inclusion(&storage, &leafs[1], &proof, &Blake2)?);
+        assert!(!TREE.check_inclusion(&storage, &leafs[2], &proof, &Blake2)?);
+
+        let proof = TREE.gen_proof_at_root(&storage, 1, &roots[4], &Blake2)?;
+
+        assert!(TREE.check_inclusion(&storage, &leafs[1], &proof, &Blake2)?);
+
+        // The proof is still correct for the evicted root, but the root is no longer valid
+        let proof = TREE
+            .tree
+            .gen_proof_at_root(&storage, 1, &roots[2], &Blake2)?;
+
+        assert!(proof.verify(&roots[2], &leafs[1], &Blake2)?);
+        assert!(!TREE.check_inclusion(&storage, &leafs[1], &proof, &Blake2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn zero_history_level() -> Result<(), Box<dyn Error>> {
+        const ZERO_HISTORY_TREE: SparseMerkleTreeWithHistoryBounded<Vec<u8>, Blake2, 0> =
+            SparseMerkleTreeWithHistoryBounded::new(
+                "hashes",
+                "leafs",
+                "level",
+                "root",
+                "root_history",
+                "root_index",
+                "history_index",
+            );
+
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let default_leaf = Blake2.hash_two(&zero_vec, &zero_vec)?;
+
+        assert!(matches!(
+            ZERO_HISTORY_TREE.init(&mut storage, 20, default_leaf.clone(), &Blake2),
+            Err(MerkleTreeError::InvalidHistoryLevel)
+        ));
+        assert!(matches!(
+            ZERO_HISTORY_TREE.update_history_level(&mut storage),
+            Err(MerkleTreeError::InvalidHistoryLevel)
+        ));
+        assert!(ZERO_HISTORY_TREE
+            .insert(&mut storage, default_leaf, &Blake2)
+            .is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,397 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::Storage;
+use cw_storage_plus::{Item, PrimaryKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Hasher, MerkleProof, MerkleTree, MerkleTreeError};
+
+use super::{BoundedHistoryNamespaces, RootHistory, SparseMerkleTree, TreeNamespaces};
+
+/// Like [SparseMerkleTreeWithHistoryBounded](super::SparseMerkleTreeWithHistoryBounded) but the history level is set on initialization.
+pub struct SparseMerkleTreeWithHistoryConfigurable<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + PrimaryKey<'a>,
+    H: Hasher<L>,
+> {
+    pub history_level: Item<'a, u32>,
+    pub history: RootHistory<'a, L>,
+    pub tree: SparseMerkleTree<'a, L, H>,
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + PrimaryKey<'a>,
+        H: Hasher<L>,
+    > SparseMerkleTreeWithHistoryConfigurable<'a, L, H>
+{
+    #[allow(clippy::too_many_arguments)]
+    pub const fn new(
+        hashes_ns: &'a str,
+        leafs_ns: &'a str,
+        level_ns: &'a str,
+        root_ns: &'a str,
+        root_history_ns: &'a str,
+        root_index_ns: &'a str,
+        history_index_ns: &'a str,
+        history_level_ns: &'a str,
+    ) -> Self {
+        Self {
+            history_level: Item::new(history_level_ns),
+            history: RootHistory::new(root_history_ns, root_index_ns, history_index_ns),
+            tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
+        }
+    }
+
+    /// Like [SparseMerkleTreeWithHistoryConfigurable::new] but with the namespaces set by name.
+    pub const fn from_namespaces(
+        tree: TreeNamespaces<'a>,
+        history: BoundedHistoryNamespaces<'a>,
+        history_level_ns: &'a str,
+    ) -> Self {
+        Self {
+            history_level: Item::new(history_level_ns),
+            history: RootHistory::from_namespaces(history),
+            tree: SparseMerkleTree::from_namespaces(tree),
+        }
+    }
+
+    /// Pin the roots still referenced like [RootHistory::with_can_evict].
+    pub const fn with_can_evict(
+        self,
+        can_evict: fn(&dyn Storage, &L) -> Result<bool, MerkleTreeError>,
+    ) -> Self {
+        Self {
+            history: self.history.with_can_evict(can_evict),
+            ..self
+        }
+    }
+
+    /// Initialize the tree like [MerkleTree::init] and keep up to `history_level` previous roots.
+    pub fn init_with_history_level(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        history_level: u32,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        (history_level > 0)
+            .then_some(())
+            .ok_or(MerkleTreeError::InvalidHistoryLevel)?;
+
+        self.tree.init(storage, level, default_leaf, hasher)?;
+        self.history_level.save(storage, &history_level)?;
+
+        Ok(())
+    }
+
+    /// Insert the `leaf` like [SparseMerkleTree::insert_no_root].
+    /// The root history is only updated on [SparseMerkleTreeWithHistoryConfigurable::commit_root].
+    pub fn insert_no_root(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<u64, MerkleTreeError> {
+        let total_inserts = self.history.next_total_inserts(storage)?;
+        let index = self.tree.insert_no_root(storage, leaf, hasher)?;
+        self.history.save_total_inserts(storage, total_inserts)?;
+
+        Ok(index)
+    }
+
+    /// Compute and save the latest root into the root history.
+    pub fn commit_root(&self, storage: &mut dyn Storage, hasher: &H) -> Result<L, MerkleTreeError> {
+        let history_level = self.history_level.load(storage)?;
+        let slot = self.history.next_slot(storage, history_level)?;
+        let latest_root = self.tree.commit_root(storage, hasher)?;
+
+        self.history.save(storage, slot, &latest_root)?;
+
+        Ok(latest_root)
+    }
+
+    /// Get the oldest root retained in the root history, which is the next root to be evicted.
+    /// Return `None` if no root is saved yet.
+    pub fn oldest_valid_root(&self, storage: &dyn Storage) -> Result<Option<L>, MerkleTreeError> {
+        let history_level = self.history_level.load(storage)?;
+
+        self.history.oldest_valid_root(storage, history_level)
+    }
+
+    /// Get the number of inserted leaves since initialization.
+    /// The counter keeps growing past the history level and is kept across [MerkleTree::reset].
+    pub fn total_inserts(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.history.total_inserts(storage)
+    }
+
+    /// Check if the `root` is valid like [MerkleTree::is_valid_root] and saved at most `max_age` inserts ago.
+    /// The age is counted with [SparseMerkleTreeWithHistoryConfigurable::total_inserts], the latest root has the age of 0.
+    /// Roots pinned by [SparseMerkleTreeWithHistoryConfigurable::with_can_evict] keep the age of when they are saved.
+    pub fn is_valid_root_within(
+        &self,
+        storage: &dyn Storage,
+        root: &L,
+        max_age: u64,
+    ) -> Result<bool, MerkleTreeError> {
+        self.history.is_valid_root_within(storage, root, max_age)
+    }
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + PrimaryKey<'a>,
+        H: Hasher<L>,
+    > MerkleTree<L, H> for SparseMerkleTreeWithHistoryConfigurable<'a, L, H>
+{
+    /// Initialize the tree with the history level of 1, only the latest root is valid.
+    /// Use [SparseMerkleTreeWithHistoryConfigurable::init_with_history_level] to keep more roots.
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.init_with_history_level(storage, level, default_leaf, 1, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        Ok(self.history.is_valid_root(storage, root))
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        Ok(self.history.roots_count(storage))
+    }
+
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        let history_level = self.history_level.load(storage)?;
+        let total_inserts = self.history.next_total_inserts(storage)?;
+        let slot = self.history.next_slot(storage, history_level)?;
+        let (index, latest_root) = self.tree.insert(storage, leaf, hasher)?;
+
+        self.history.save_total_inserts(storage, total_inserts)?;
+        self.history.save(storage, slot, &latest_root)?;
+
+        Ok((index, latest_root))
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        let history_level = self.history_level.load(storage)?;
+        let total_inserts = self.history.next_total_inserts(storage)?;
+        let slot = self.history.next_slot(storage, history_level)?;
+        let (index, latest_root, proof) =
+            self.tree.insert_returning_proof(storage, leaf, hasher)?;
+
+        self.history.save_total_inserts(storage, total_inserts)?;
+        self.history.save(storage, slot, &latest_root)?;
+
+        Ok((index, latest_root, proof))
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)?;
+        self.history.clear(storage)?;
+        self.history_level.remove(storage);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{test_utils::Blake2, Hasher, MerkleTree, MerkleTreeError};
+
+    use super::SparseMerkleTreeWithHistoryConfigurable;
+
+    const TREE: SparseMerkleTreeWithHistoryConfigurable<Vec<u8>, Blake2> =
+        SparseMerkleTreeWithHistoryConfigurable::new(
+            "hashes",
+            "leafs",
+            "level",
+            "root",
+            "root_history",
+            "root_index",
+            "history_index",
+            "history_level",
+        );
+    const ZERO: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0,
+    ];
+
+    #[test]
+    fn init() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+
+        assert!(matches!(
+            TREE.init_with_history_level(
+                &mut storage,
+                20,
+                Blake2.hash_two(&zero_vec, &zero_vec)?,
+                0,
+                &Blake2,
+            ),
+            Err(MerkleTreeError::InvalidHistoryLevel)
+        ));
+
+        TREE.init_with_history_level(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            3,
+            &Blake2,
+        )?;
+
+        assert_eq!(TREE.history_level.load(&storage)?, 3);
+        assert_eq!(TREE.oldest_valid_root(&storage)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn root_history() -> Result<(), Box<dyn Error>> {
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+
+        for history_level in [3, 5] {
+            let mut storage = MockStorage::new();
+
+            TREE.init_with_history_level(
+                &mut storage,
+                20,
+                Blake2.hash_two(&zero_vec, &zero_vec)?,
+                history_level,
+                &Blake2,
+            )?;
+
+            let mut roots = vec![];
+
+            for _ in 0..8 {
+                roots.push(TREE.insert(&mut storage, leaf.clone(), &Blake2)?.1);
+            }
+
+            let (evicted, retained) = roots.split_at(8 - history_level as usize);
+
+            for root in evicted {
+                assert!(!TREE.is_valid_root(&storage, root)?);
+            }
+            for root in retained {
+                assert!(TREE.is_valid_root(&storage, root)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn oldest_valid_root() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init_with_history_level(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            3,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+        let mut roots = vec![];
+
+        for _ in 0..2 {
+            roots.push(TREE.insert(&mut storage, leaf.clone(), &Blake2)?.1);
+        }
+
+        assert_eq!(TREE.oldest_valid_root(&storage)?, Some(roots[0].clone()));
+
+        for _ in 0..3 {
+            roots.push(TREE.insert(&mut storage, leaf.clone(), &Blake2)?.1);
+        }
+
+        assert_eq!(TREE.oldest_valid_root(&storage)?, Some(roots[2].clone()));
+        assert!(!TREE.is_valid_root(&storage, &roots[1])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reset() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+        let default_leaf = Blake2.hash_two(&zero_vec, &zero_vec)?;
+
+        TREE.init_with_history_level(&mut storage, 20, default_leaf.clone(), 5, &Blake2)?;
+
+        let (_, old_root) =
+            TREE.insert(&mut storage, Blake2.hash_two(&one_vec, &one_vec)?, &Blake2)?;
+
+        TREE.reset(&mut storage)?;
+
+        assert!(!TREE.is_valid_root(&storage, &old_root)?);
+        assert!(TREE.history_level.may_load(&storage)?.is_none());
+
+        TREE.init_with_history_level(&mut storage, 20, default_leaf, 3, &Blake2)?;
+
+        assert_eq!(TREE.history_level.load(&storage)?, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::Storage;
+use cw_storage_plus::{Map, PrimaryKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{algorithm, Hasher, MerkleProof, MerkleTree, MerkleTreeError, TreeState};
+
+use super::{SparseMerkleTree, TreeNamespaces};
+
+/// Like [SparseMerkleTree] but also maintains a reverse index from leaf to its first inserted index.
+pub struct SparseMerkleTreeWithLeafIndex<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + PrimaryKey<'a>,
+    H: Hasher<L>,
+> {
+    pub tree: SparseMerkleTree<'a, L, H>,
+    pub leaf_index: Map<'a, L, u64>,
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + PrimaryKey<'a>,
+        H: Hasher<L>,
+    > SparseMerkleTreeWithLeafIndex<'a, L, H>
+{
+    pub const fn new(
+        hashes_ns: &'a str,
+        leafs_ns: &'a str,
+        level_ns: &'a str,
+        root_ns: &'a str,
+        leaf_index_ns: &'a str,
+    ) -> Self {
+        Self {
+            tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
+            leaf_index: Map::new(leaf_index_ns),
+        }
+    }
+
+    /// Like [SparseMerkleTreeWithLeafIndex::new] but with the tree namespaces set by name.
+    pub const fn from_namespaces(tree: TreeNamespaces<'a>, leaf_index_ns: &'a str) -> Self {
+        Self {
+            tree: SparseMerkleTree::from_namespaces(tree),
+            leaf_index: Map::new(leaf_index_ns),
+        }
+    }
+
+    /// Get the first index the `leaf` was inserted at, if any.
+    pub fn index_of_leaf(
+        &self,
+        storage: &dyn Storage,
+        leaf: &L,
+    ) -> Result<Option<u64>, MerkleTreeError> {
+        Ok(self.leaf_index.may_load(storage, leaf.clone())?)
+    }
+
+    /// Check if the `leaf` was ever inserted into the tree.
+    pub fn contains_leaf(&self, storage: &dyn Storage, leaf: &L) -> Result<bool, MerkleTreeError> {
+        Ok(self.leaf_index.has(storage, leaf.clone()))
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert] but reject the leaf that is already inserted.
+    pub fn insert_unique(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        (!self.contains_leaf(storage, &leaf)?)
+            .then_some(())
+            .ok_or(MerkleTreeError::DuplicateLeaf)?;
+
+        self.insert(storage, leaf, hasher)
+    }
+
+    /// Initialize the tree and insert all `leaves` like [SparseMerkleTree::init_with_leaves].
+    pub fn init_with_leaves(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        leaves: Vec<L>,
+        hasher: &H,
+    ) -> Result<L, MerkleTreeError> {
+        algorithm::count_fits(leaves.len() as u64, level as usize)
+            .then_some(())
+            .ok_or(MerkleTreeError::ExceedMaxLeaf)?;
+
+        self.init(storage, level, default_leaf, hasher)?;
+
+        for leaf in leaves {
+            self.insert_no_root(storage, leaf, hasher)?;
+        }
+
+        self.commit_root(storage, hasher)
+    }
+
+    /// Export the full state of the tree like [SparseMerkleTree::export_state].
+    pub fn export_state(&self, storage: &dyn Storage) -> Result<TreeState<L>, MerkleTreeError> {
+        self.tree.export_state(storage)
+    }
+
+    /// Import the exported `state` like [SparseMerkleTree::import_state] and rebuild the leaf index.
+    pub fn import_state(
+        &self,
+        storage: &mut dyn Storage,
+        state: TreeState<L>,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        let leafs = state.leafs.clone();
+
+        self.tree.import_state(storage, state, hasher)?;
+
+        for (index, leaf) in leafs {
+            self.save_leaf_index(storage, leaf, index)?;
+        }
+
+        Ok(())
+    }
+
+    /// Deep copy the tree like [SparseMerkleTree::snapshot_to] and rebuild the leaf index of `dst`.
+    pub fn snapshot_to(
+        &self,
+        storage: &mut dyn Storage,
+        dst: &Self,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.snapshot_to(storage, &dst.tree)?;
+
+        for (index, leaf) in dst.tree.leaves_in_range(storage, None, usize::MAX)? {
+            dst.save_leaf_index(storage, leaf, index)?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert the `leaf` like [SparseMerkleTree::insert_no_root] while maintaining the leaf index.
+    pub fn insert_no_root(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<u64, MerkleTreeError> {
+        let index = self.tree.insert_no_root(storage, leaf.clone(), hasher)?;
+
+        self.save_leaf_index(storage, leaf, index)?;
+
+        Ok(index)
+    }
+
+    /// Compute and save the latest root like [SparseMerkleTree::commit_root].
+    pub fn commit_root(&self, storage: &mut dyn Storage, hasher: &H) -> Result<L, MerkleTreeError> {
+        self.tree.commit_root(storage, hasher)
+    }
+
+    fn save_leaf_index(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        index: u64,
+    ) -> Result<(), MerkleTreeError> {
+        // Keep the first index for duplicated leaf
+        if !self.leaf_index.has(storage, leaf.clone()) {
+            self.leaf_index.save(storage, leaf, &index)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + PrimaryKey<'a>,
+        H: Hasher<L>,
+    > MerkleTree<L, H> for SparseMerkleTreeWithLeafIndex<'a, L, H>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.init(storage, level, default_leaf, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        self.tree.is_valid_root(storage, root)
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.roots_count(storage)
+    }
+
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        let (index, latest_root) = self.tree.insert(storage, leaf.clone(), hasher)?;
+
+        self.save_leaf_index(storage, leaf, index)?;
+
+        Ok((index, latest_root))
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        let (index, latest_root, proof) =
+            self.tree
+                .insert_returning_proof(storage, leaf.clone(), hasher)?;
+
+        self.save_leaf_index(storage, leaf, index)?;
+
+        Ok((index, latest_root, proof))
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)?;
+        self.leaf_index.clear(storage);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{test_utils::Blake2, Hasher, MerkleTree, MerkleTreeError};
+
+    use super::SparseMerkleTreeWithLeafIndex;
+
+    const TREE: SparseMerkleTreeWithLeafIndex<Vec<u8>, Blake2> =
+        SparseMerkleTreeWithLeafIndex::new("hashes", "leafs", "level", "root", "leaf_index");
+    const ZERO: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0,
+    ];
+
+    #[test]
+    fn index_of_leaf() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+        let two_vec = Uint256::from_u128(2).to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let leaf_one = Blake2.hash_two(&one_vec, &one_vec)?;
+        let leaf_two = Blake2.hash_two(&two_vec, &two_vec)?;
+        let missing_leaf = Blake2.hash_two(&one_vec, &two_vec)?;
+
+        TREE.insert(&mut storage, leaf_one.clone(), &Blake2)?;
+        TREE.insert(&mut storage, leaf_two.clone(), &Blake2)?;
+        TREE.insert(&mut storage, leaf_one.clone(), &Blake2)?;
+
+        assert_eq!(TREE.index_of_leaf(&storage, &leaf_one)?, Some(0));
+        assert_eq!(TREE.index_of_leaf(&storage, &leaf_two)?, Some(1));
+        assert_eq!(TREE.index_of_leaf(&storage, &missing_leaf)?, None);
+        assert!(TREE.contains_leaf(&storage, &leaf_one)?);
+        assert!(!TREE.contains_leaf(&storage, &missing_leaf)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_unique() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+        let zero_vec = ZERO.to_vec();
+        let one_vec = Uint256::one().to_be_bytes().to_vec();
+
+        TREE.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&zero_vec, &zero_vec)?,
+            &Blake2,
+        )?;
+
+        let leaf = Blake2.hash_two(&one_vec, &one_vec)?;
+
+        let (index, root) = TREE.insert_unique(&mut storage, leaf.clone(), &Blake2)?;
+
+        assert_eq!(index, 0);
+        assert!(matches!(
+            TREE.insert_unique(&mut storage, leaf.clone(), &Blake2),
+            Err(MerkleTreeError::DuplicateLeaf)
+        ));
+        assert_eq!(TREE.get_latest_root(&storage)?, root);
+        assert_eq!(TREE.index_of_leaf(&storage, &leaf)?, Some(0));
+        assert!(!TREE.tree.leafs.has(&storage, 1));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use cosmwasm_std::Storage;
+use cw_storage_plus::Map;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Hasher, MerkleProof, MerkleTree, MerkleTreeError};
+
+use super::{SparseMerkleTree, TreeNamespaces};
+
+/// Like [SparseMerkleTree] but also stores the metadata `M` of each leaf keyed by its index, e.g. the depositor and the amount.
+pub struct SparseMerkleTreeWithMetadata<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+    H: Hasher<L>,
+    M: Serialize + DeserializeOwned,
+> {
+    _m: PhantomData<M>,
+    pub tree: SparseMerkleTree<'a, L, H>,
+    pub metadata: Map<'a, u64, M>,
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+        H: Hasher<L>,
+        M: Serialize + DeserializeOwned,
+    > SparseMerkleTreeWithMetadata<'a, L, H, M>
+{
+    pub const fn new(
+        hashes_ns: &'a str,
+        leafs_ns: &'a str,
+        level_ns: &'a str,
+        root_ns: &'a str,
+        metadata_ns: &'a str,
+    ) -> Self {
+        Self {
+            _m: PhantomData,
+            tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
+            metadata: Map::new(metadata_ns),
+        }
+    }
+
+    /// Like [SparseMerkleTreeWithMetadata::new] but with the tree namespaces set by name.
+    pub const fn from_namespaces(tree: TreeNamespaces<'a>, metadata_ns: &'a str) -> Self {
+        Self {
+            _m: PhantomData,
+            tree: SparseMerkleTree::from_namespaces(tree),
+            metadata: Map::new(metadata_ns),
+        }
+    }
+
+    /// Insert the `leaf` like [MerkleTree::insert] and save the `meta` at the leaf's index.
+    /// Nothing is saved if the insertion fails.
+    pub fn insert_with_meta(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        meta: M,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        let (index, latest_root) = self.tree.insert(storage, leaf, hasher)?;
+
+        self.metadata.save(storage, index, &meta)?;
+
+        Ok((index, latest_root))
+    }
+
+    /// Get the metadata of the leaf at `index`, return `None` if the leaf is inserted without metadata or not inserted.
+    pub fn get_meta(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+    ) -> Result<Option<M>, MerkleTreeError> {
+        Ok(self.metadata.may_load(storage, index)?)
+    }
+}
+
+impl<
+        'a,
+        L: Serialize + DeserializeOwned + Clone + Debug + PartialEq,
+        H: Hasher<L>,
+        M: Serialize + DeserializeOwned,
+    > MerkleTree<L, H> for SparseMerkleTreeWithMetadata<'a, L, H, M>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree.init(storage, level, default_leaf, hasher)
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        self.tree.is_valid_root(storage, root)
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.roots_count(storage)
+    }
+
+    /// Insert the `leaf` without metadata, use [SparseMerkleTreeWithMetadata::insert_with_meta] to save it.
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        self.tree.insert(storage, leaf, hasher)
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        self.tree.insert_returning_proof(storage, leaf, hasher)
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree.prove_and_verify(storage, index, hasher)
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)?;
+        self.metadata.clear(storage);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use cosmwasm_std::{testing::MockStorage, Addr, Uint128, Uint256};
+
+    use crate::{test_utils::Blake2, Hasher, MerkleTree, MerkleTreeError};
+
+    use super::SparseMerkleTreeWithMetadata;
+
+    const TREE: SparseMerkleTreeWithMetadata<Uint256, Blake2, (Addr, Uint128)> =
+        SparseMerkleTreeWithMetadata::new("hashes", "leafs", "level", "root", "metadata");
+
+    #[test]
+    fn insert_with_meta() -> Result<(), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        TREE.init(
+            &mut storage,
+            1,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        let leafs = (1..=3)
+            .map(|i| Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let alice = (Addr::unchecked("alice"), Uint128::new(100));
+
+        let (index, _) = TREE.insert_with_meta(&mut storage, leafs[0], alice.clone(), &Blake2)?;
+        TREE.insert(&mut storage, leafs[1], &Blake2)?;
+
+        assert_eq!(index, 0);
+
+        assert_eq!(TREE.get_leaf(&storage, 0)?, Some(leafs[0]));
+        assert_eq!(TREE.get_meta(&storage, 0)?, Some(alice));
+        assert_eq!(TREE.get_leaf(&storage, 1)?, Some(leafs[1]));
+        assert_eq!(TREE.get_meta(&storage, 1)?, None);
+
+        // The metadata is not saved when the tree is full
+        assert!(matches!(
+            TREE.insert_with_meta(
+                &mut storage,
+                leafs[2],
+                (Addr::unchecked("bob"), Uint128::new(1)),
+                &Blake2
+            ),
+            Err(MerkleTreeError::ExceedMaxLeaf)
+        ));
+        assert_eq!(TREE.get_meta(&storage, 2)?, None);
+
+        Ok(())
+    }
+}
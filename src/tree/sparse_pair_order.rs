@@ -0,0 +1,288 @@
+use std::fmt::Debug;
+
+use cosmwasm_std::Storage;
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{Hasher, HasherError, MerkleProof, MerkleTree, MerkleTreeError};
+
+use super::{SparseMerkleTree, TreeNamespaces};
+
+/// Order of the two children when hashing them into their parent.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PairOrder {
+    /// Hash the left child before the right child, the order used by [SparseMerkleTree].
+    Positional,
+    /// Hash the smaller child before the greater child, like OpenZeppelin's `MerkleProof`.
+    Sorted,
+}
+
+/// Hasher hashing the pairs in the `order`, every other hook is forwarded to the inner `hasher`.
+#[derive(Clone, Debug)]
+pub struct PairOrderHasher<H> {
+    pub hasher: H,
+    pub order: PairOrder,
+}
+
+impl<L: Ord, H: Hasher<L>> Hasher<L> for PairOrderHasher<H> {
+    fn hash_two(&self, left: &L, right: &L) -> Result<L, HasherError> {
+        match self.order == PairOrder::Sorted && left > right {
+            true => self.hasher.hash_two(right, left),
+            false => self.hasher.hash_two(left, right),
+        }
+    }
+
+    fn hash_two_owned(&self, left: L, right: L) -> Result<L, HasherError> {
+        match self.order == PairOrder::Sorted && left > right {
+            true => self.hasher.hash_two_owned(right, left),
+            false => self.hasher.hash_two_owned(left, right),
+        }
+    }
+
+    fn hash_leaf(&self, preimage: &L) -> Result<L, HasherError> {
+        self.hasher.hash_leaf(preimage)
+    }
+
+    fn validate_leaf(&self, leaf: &L) -> Result<(), MerkleTreeError> {
+        self.hasher.validate_leaf(leaf)
+    }
+}
+
+/// Like [SparseMerkleTree] but hashes every pair of children in the configured [PairOrder].
+pub struct SparseMerkleTreeWithPairOrder<
+    'a,
+    L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + Ord,
+    H: Hasher<L>,
+> {
+    pub tree: SparseMerkleTree<'a, L, PairOrderHasher<H>>,
+    pub pair_order: PairOrder,
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + Ord, H: Hasher<L>>
+    SparseMerkleTreeWithPairOrder<'a, L, H>
+{
+    pub const fn new(
+        hashes_ns: &'a str,
+        leafs_ns: &'a str,
+        level_ns: &'a str,
+        root_ns: &'a str,
+        pair_order: PairOrder,
+    ) -> Self {
+        Self {
+            tree: SparseMerkleTree::new(hashes_ns, leafs_ns, level_ns, root_ns),
+            pair_order,
+        }
+    }
+
+    /// Like [SparseMerkleTreeWithPairOrder::new] but with the tree namespaces set by name.
+    pub const fn from_namespaces(tree: TreeNamespaces<'a>, pair_order: PairOrder) -> Self {
+        Self {
+            tree: SparseMerkleTree::from_namespaces(tree),
+            pair_order,
+        }
+    }
+
+    /// Wrap the `hasher` into the hasher used by the inner tree.
+    /// Use it to verify the proofs with [MerkleProof::verify].
+    pub fn hasher(&self, hasher: &H) -> PairOrderHasher<H> {
+        PairOrderHasher {
+            hasher: hasher.clone(),
+            order: self.pair_order,
+        }
+    }
+
+    /// Generate the proof of the leaf at `index` like [SparseMerkleTree::gen_proof].
+    pub fn gen_proof(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<MerkleProof<L>, MerkleTreeError> {
+        self.tree.gen_proof(storage, index, &self.hasher(hasher))
+    }
+}
+
+impl<'a, L: Serialize + DeserializeOwned + Clone + Debug + PartialEq + Ord, H: Hasher<L>>
+    MerkleTree<L, H> for SparseMerkleTreeWithPairOrder<'a, L, H>
+{
+    fn init(
+        &self,
+        storage: &mut dyn Storage,
+        level: u8,
+        default_leaf: L,
+        hasher: &H,
+    ) -> Result<(), MerkleTreeError> {
+        self.tree
+            .init(storage, level, default_leaf, &self.hasher(hasher))
+    }
+
+    fn is_valid_root(&self, storage: &dyn Storage, root: &L) -> Result<bool, MerkleTreeError> {
+        self.tree.is_valid_root(storage, root)
+    }
+
+    fn roots_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.roots_count(storage)
+    }
+
+    fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L), MerkleTreeError> {
+        self.tree.insert(storage, leaf, &self.hasher(hasher))
+    }
+
+    fn insert_returning_proof(
+        &self,
+        storage: &mut dyn Storage,
+        leaf: L,
+        hasher: &H,
+    ) -> Result<(u64, L, MerkleProof<L>), MerkleTreeError> {
+        self.tree
+            .insert_returning_proof(storage, leaf, &self.hasher(hasher))
+    }
+
+    fn get_latest_root(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.get_latest_root(storage)
+    }
+
+    fn get_leaf(&self, storage: &dyn Storage, index: u64) -> Result<Option<L>, MerkleTreeError> {
+        self.tree.get_leaf(storage, index)
+    }
+
+    fn level(&self, storage: &dyn Storage) -> Result<u8, MerkleTreeError> {
+        self.tree.level(storage)
+    }
+
+    fn default_leaf(&self, storage: &dyn Storage) -> Result<L, MerkleTreeError> {
+        self.tree.default_leaf(storage)
+    }
+
+    fn zeros(&self, storage: &dyn Storage) -> Result<Vec<L>, MerkleTreeError> {
+        self.tree.zeros(storage)
+    }
+
+    fn leaf_count(&self, storage: &dyn Storage) -> Result<u64, MerkleTreeError> {
+        self.tree.leaf_count(storage)
+    }
+
+    fn is_full(&self, storage: &dyn Storage) -> Result<bool, MerkleTreeError> {
+        self.tree.is_full(storage)
+    }
+
+    fn prove_and_verify(
+        &self,
+        storage: &dyn Storage,
+        index: u64,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree
+            .prove_and_verify(storage, index, &self.hasher(hasher))
+    }
+
+    fn check_inclusion(
+        &self,
+        storage: &dyn Storage,
+        leaf: &L,
+        proof: &MerkleProof<L>,
+        hasher: &H,
+    ) -> Result<bool, MerkleTreeError> {
+        self.tree
+            .check_inclusion(storage, leaf, proof, &self.hasher(hasher))
+    }
+
+    fn reset(&self, storage: &mut dyn Storage) -> Result<(), MerkleTreeError> {
+        self.tree.reset(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, str::FromStr};
+
+    use cosmwasm_std::{testing::MockStorage, Uint256};
+
+    use crate::{test_utils::Blake2, Hasher, MerkleTree};
+
+    use super::{PairOrder, SparseMerkleTreeWithPairOrder};
+
+    const POSITIONAL_TREE: SparseMerkleTreeWithPairOrder<Uint256, Blake2> =
+        SparseMerkleTreeWithPairOrder::new(
+            "hashes",
+            "leafs",
+            "level",
+            "root",
+            PairOrder::Positional,
+        );
+
+    const SORTED_TREE: SparseMerkleTreeWithPairOrder<Uint256, Blake2> =
+        SparseMerkleTreeWithPairOrder::new("hashes", "leafs", "level", "root", PairOrder::Sorted);
+
+    fn insert_leafs(
+        tree: &SparseMerkleTreeWithPairOrder<Uint256, Blake2>,
+    ) -> Result<(MockStorage, Uint256), Box<dyn Error>> {
+        let mut storage = MockStorage::new();
+
+        tree.init(
+            &mut storage,
+            20,
+            Blake2.hash_two(&Uint256::zero(), &Uint256::zero())?,
+            &Blake2,
+        )?;
+
+        for i in 1..=5 {
+            let leaf = Blake2.hash_two(&Uint256::from_u128(i), &Uint256::from_u128(i))?;
+            tree.insert(&mut storage, leaf, &Blake2)?;
+        }
+
+        let root = tree.get_latest_root(&storage)?;
+
+        Ok((storage, root))
+    }
+
+    #[test]
+    fn positional() -> Result<(), Box<dyn Error>> {
+        let (storage, root) = insert_leafs(&POSITIONAL_TREE)?;
+
+        assert_eq!(
+            root,
+            Uint256::from_str(
+                "100259425028802178177846186514296156840305931743209248638432749269113773377665"
+            )?
+        );
+
+        for index in 0..5 {
+            assert!(POSITIONAL_TREE.prove_and_verify(&storage, index, &Blake2)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn sorted() -> Result<(), Box<dyn Error>> {
+        let (storage, root) = insert_leafs(&SORTED_TREE)?;
+
+        assert_eq!(
+            root,
+            Uint256::from_str(
+                "76606785553731269529753424551292890575906762989000145746440714407251501479781"
+            )?
+        );
+
+        let hasher = SORTED_TREE.hasher(&Blake2);
+
+        for index in 0..5 {
+            let leaf = SORTED_TREE.get_leaf(&storage, index)?.unwrap();
+            let mut proof = SORTED_TREE.gen_proof(&storage, index, &Blake2)?;
+
+            assert!(proof.verify(&root, &leaf, &hasher)?);
+
+            // The directions are irrelevant once the pairs are sorted.
+            proof.index ^= 1;
+            assert!(proof.verify(&root, &leaf, &hasher)?);
+        }
+
+        Ok(())
+    }
+}